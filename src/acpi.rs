@@ -0,0 +1,336 @@
+// file:	acpi.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Just enough ACPI table parsing to find the interrupt controllers.
+
+// includes
+use core::mem::size_of;
+use core::ptr::read_unaligned;
+use core::slice;
+use lazy_static::lazy_static;
+use x86_64::PhysAddr;
+use crate::memory::mmio::map_physical;
+
+/// max number of I/O APICs we keep track of
+pub const MAX_IO_APICS: usize = 4;
+/// max number of interrupt source overrides we keep track of
+pub const MAX_OVERRIDES: usize = 16;
+/// max number of local APIC NMI entries we keep track of
+pub const MAX_LOCAL_NMIS: usize = 4;
+
+// the longest table we'll believe. the ones we read are a few hundred bytes,
+// so anything past this is the firmware lying to us.
+const MAX_TABLE_LEN: u64 = 1024 * 1024;
+
+// Rsdp is the root system description pointer, which the firmware leaves
+// somewhere in low memory for us to go find
+#[repr(C, packed)]
+struct Rsdp {
+	signature: [u8; 8],
+	checksum: u8,
+	oem_id: [u8; 6],
+	revision: u8,
+	rsdt_address: u32,
+	// everything past here only exists for revision 2 and up
+	length: u32,
+	xsdt_address: u64,
+	extended_checksum: u8,
+	reserved: [u8; 3],
+}
+
+// SdtHeader is the header every ACPI table starts with
+#[repr(C, packed)]
+struct SdtHeader {
+	signature: [u8; 4],
+	length: u32,
+	revision: u8,
+	checksum: u8,
+	oem_id: [u8; 6],
+	oem_table_id: [u8; 8],
+	oem_revision: u32,
+	creator_id: u32,
+	creator_revision: u32,
+}
+
+// Polarity is the polarity of an interrupt input, as ACPI describes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+	ConformToBus,
+	ActiveHigh,
+	ActiveLow,
+}
+
+// TriggerMode is the trigger mode of an interrupt input, as ACPI describes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+	ConformToBus,
+	Edge,
+	Level,
+}
+
+// IoApicInfo is an I/O APIC entry from the MADT
+#[derive(Debug, Clone, Copy)]
+pub struct IoApicInfo {
+	pub id: u8,
+	pub address: u32,
+	pub gsi_base: u32,
+}
+
+// InterruptOverride is an ISA IRQ that isn't identity-mapped to a GSI
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptOverride {
+	pub source: u8,
+	pub gsi: u32,
+	pub polarity: Polarity,
+	pub trigger: TriggerMode,
+}
+
+// LocalApicNmi is a local APIC LINT pin that's wired to NMI
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApicNmi {
+	// 0xff means every processor
+	pub processor: u8,
+	pub lint: u8,
+	pub polarity: Polarity,
+	pub trigger: TriggerMode,
+}
+
+// Madt is everything we care about from the multiple APIC description table
+#[derive(Debug)]
+pub struct Madt {
+	pub local_apic_address: u64,
+	// whether there's also a legacy PIC pair that needs masking
+	pub pic_present: bool,
+	pub cpu_count: usize,
+	pub io_apics: [Option<IoApicInfo>; MAX_IO_APICS],
+	pub overrides: [Option<InterruptOverride>; MAX_OVERRIDES],
+	pub local_nmis: [Option<LocalApicNmi>; MAX_LOCAL_NMIS],
+}
+
+impl Madt {
+	/// io_apics() iterates over every I/O APIC that was found
+	pub fn io_apics(&self) -> impl Iterator<Item = &IoApicInfo> {
+		self.io_apics.iter().filter_map(Option::as_ref)
+	}
+
+	/// isa_override() returns the override for an ISA IRQ, if there is one
+	pub fn isa_override(&self, irq: u8) -> Option<&InterruptOverride> {
+		self.overrides.iter().filter_map(Option::as_ref)
+			.find(|entry| entry.source == irq)
+	}
+
+	/// local_nmis() iterates over every local APIC NMI entry
+	pub fn local_nmis(&self) -> impl Iterator<Item = &LocalApicNmi> {
+		self.local_nmis.iter().filter_map(Option::as_ref)
+	}
+}
+
+// the MADT gets parsed the first time anyone asks for it
+lazy_static! {
+	static ref MADT: Option<Madt> = unsafe { find_rsdp().and_then(parse_madt) };
+}
+
+/// madt() returns the parsed MADT, or None if the firmware doesn't have one
+pub fn madt() -> Option<&'static Madt> {
+	MADT.as_ref()
+}
+
+/// checksum_ok() checks an ACPI checksum, which is that the bytes sum to 0
+fn checksum_ok(bytes: &[u8]) -> bool {
+	bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// find_rsdp() looks for the RSDP in the first KiB of the EBDA and in the
+/// BIOS area at 0xe0000-0xfffff, which is everywhere the spec lets it be
+unsafe fn find_rsdp() -> Option<Rsdp> {
+	// the real-mode segment of the EBDA is stored at 0x40e
+	let ebda_segment = read_unaligned(
+		map_physical(PhysAddr::new(0x40e), 2).as_ptr::<u16>());
+	let ebda = (ebda_segment as u64) << 4;
+
+	let areas = [(ebda, 1024), (0xe0000, 0x20000)];
+	for &(start, len) in areas.iter() {
+		if start == 0 {
+			continue;
+		}
+
+		// the RSDP is always on a 16-byte bound
+		let area = slice::from_raw_parts(
+			map_physical(PhysAddr::new(start), len).as_ptr::<u8>(),
+			len as usize);
+		for candidate in area.chunks(16) {
+			if &candidate[..8] != b"RSD PTR " {
+				continue;
+			}
+
+			// only the first 20 bytes are covered by the original checksum
+			let offset = candidate.as_ptr() as usize - area.as_ptr() as usize;
+			if offset + size_of::<Rsdp>() > area.len()
+				|| !checksum_ok(&area[offset..offset + 20])
+			{
+				continue;
+			}
+			return Some(read_unaligned(candidate.as_ptr() as *const Rsdp));
+		}
+	}
+	None
+}
+
+/// map_table() maps a whole ACPI table and returns it as bytes, or None if
+/// its length makes no sense or its checksum is bad
+unsafe fn map_table(phys: u64) -> Option<&'static [u8]> {
+	// map the header first to find out how long the table is
+	let header_len = size_of::<SdtHeader>() as u64;
+	let header = map_physical(PhysAddr::new(phys), header_len);
+	let len = read_unaligned(header.as_ptr::<SdtHeader>()).length as u64;
+	if len < header_len || len > MAX_TABLE_LEN {
+		return None;
+	}
+
+	// mappings are permanent, so only map the table again if it runs past
+	// the pages the header's mapping already covers, which it usually won't
+	let mapped = ((phys + header_len + 0xfff) & !0xfff) - phys;
+	let start = if len <= mapped {
+		header
+	} else {
+		map_physical(PhysAddr::new(phys), len)
+	};
+	let table = slice::from_raw_parts(start.as_ptr::<u8>(), len as usize);
+
+	if checksum_ok(table) {
+		Some(table)
+	} else {
+		None
+	}
+}
+
+/// find_table() walks the RSDT (or XSDT, if there is one) for a table
+unsafe fn find_table(rsdp: &Rsdp, signature: &[u8; 4])
+	-> Option<&'static [u8]>
+{
+	// the XSDT has 64-bit pointers, the RSDT 32-bit ones
+	let (root, entry_size) = if rsdp.revision >= 2 && rsdp.xsdt_address != 0 {
+		(map_table(rsdp.xsdt_address)?, 8)
+	} else {
+		(map_table(rsdp.rsdt_address as u64)?, 4)
+	};
+
+	for entry in root[size_of::<SdtHeader>()..].chunks(entry_size) {
+		if entry.len() != entry_size {
+			break;
+		}
+		let phys = if entry_size == 8 {
+			read_unaligned(entry.as_ptr() as *const u64)
+		} else {
+			read_unaligned(entry.as_ptr() as *const u32) as u64
+		};
+
+		let table = match map_table(phys) {
+			Some(table) => table,
+			None => continue,
+		};
+		if &table[..4] == signature {
+			return Some(table);
+		}
+	}
+	None
+}
+
+/// inti_flags() decodes the MPS INTI flags used by the MADT
+fn inti_flags(flags: u16) -> (Polarity, TriggerMode) {
+	let polarity = match flags & 0b11 {
+		0b01 => Polarity::ActiveHigh,
+		0b11 => Polarity::ActiveLow,
+		_ => Polarity::ConformToBus,
+	};
+	let trigger = match (flags >> 2) & 0b11 {
+		0b01 => TriggerMode::Edge,
+		0b11 => TriggerMode::Level,
+		_ => TriggerMode::ConformToBus,
+	};
+	(polarity, trigger)
+}
+
+/// parse_madt() finds the MADT and pulls the interesting entries out of it
+unsafe fn parse_madt(rsdp: Rsdp) -> Option<Madt> {
+	let table = find_table(&rsdp, b"APIC")?;
+	let header_len = size_of::<SdtHeader>();
+	// the local APIC address and flags come before any entries
+	if table.len() < header_len + 8 {
+		return None;
+	}
+	let read_u16 = |offset: usize| {
+		read_unaligned(table[offset..].as_ptr() as *const u16)
+	};
+	let read_u32 = |offset: usize| {
+		read_unaligned(table[offset..].as_ptr() as *const u32)
+	};
+
+	let mut madt = Madt {
+		local_apic_address: read_u32(header_len) as u64,
+		pic_present: read_u32(header_len + 4) & 1 != 0,
+		cpu_count: 0,
+		io_apics: [None; MAX_IO_APICS],
+		overrides: [None; MAX_OVERRIDES],
+		local_nmis: [None; MAX_LOCAL_NMIS],
+	};
+	let mut io_apic_count = 0;
+	let mut override_count = 0;
+	let mut nmi_count = 0;
+
+	// the entries are variable-length, each starting with a type and length
+	let mut offset = header_len + 8;
+	while offset + 2 <= table.len() {
+		let entry_type = table[offset];
+		let entry_len = table[offset + 1] as usize;
+		if entry_len < 2 || offset + entry_len > table.len() {
+			break;
+		}
+
+		match entry_type {
+			// processor local APIC. bit 0 of the flags means it's usable
+			0 if read_u32(offset + 4) & 1 != 0 => madt.cpu_count += 1,
+			// I/O APIC
+			1 if io_apic_count < MAX_IO_APICS => {
+				madt.io_apics[io_apic_count] = Some(IoApicInfo {
+					id: table[offset + 2],
+					address: read_u32(offset + 4),
+					gsi_base: read_u32(offset + 8),
+				});
+				io_apic_count += 1;
+			},
+			// interrupt source override. only bus 0 (ISA) is defined
+			2 if override_count < MAX_OVERRIDES => {
+				let (polarity, trigger) = inti_flags(read_u16(offset + 8));
+				madt.overrides[override_count] = Some(InterruptOverride {
+					source: table[offset + 3],
+					gsi: read_u32(offset + 4),
+					polarity,
+					trigger,
+				});
+				override_count += 1;
+			},
+			// local APIC NMI
+			4 if nmi_count < MAX_LOCAL_NMIS => {
+				let (polarity, trigger) = inti_flags(read_u16(offset + 3));
+				madt.local_nmis[nmi_count] = Some(LocalApicNmi {
+					processor: table[offset + 2],
+					lint: table[offset + 5],
+					polarity,
+					trigger,
+				});
+				nmi_count += 1;
+			},
+			// local APIC address override, for 64-bit addresses
+			5 => {
+				madt.local_apic_address = read_unaligned(
+					table[offset + 4..].as_ptr() as *const u64);
+			},
+			_ => {},
+		}
+
+		offset += entry_len;
+	}
+
+	Some(madt)
+}
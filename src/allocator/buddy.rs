@@ -0,0 +1,164 @@
+// file:	buddy.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	A buddy allocator. every block is a power of two in size, split in
+//			half to fit smaller allocations and merged back with its buddy
+//			when both halves are free.
+
+// includes
+use core::alloc::Layout;
+use core::ptr;
+use super::HeapAllocator;
+
+// the smallest block is 16 bytes, which is plenty for a free block's link
+const MIN_ORDER: usize = 4;
+// one free list per power of two a usize can hold
+const ORDERS: usize = 64;
+
+// FreeBlock is written at the start of every block on a free list
+struct FreeBlock {
+	next: *mut FreeBlock,
+}
+
+/// BuddyAllocator hands out power of two sized blocks. it wastes some of
+/// every allocation that isn't a power of two, but merging buddies keeps
+/// the free memory in big pieces.
+pub struct BuddyAllocator {
+	// where the heap starts. blocks are aligned to their size relative to
+	// this, so it's what buddies are worked out from.
+	base: usize,
+	// the free list for each order. order n holds 2^n byte blocks.
+	free_lists: [*mut FreeBlock; ORDERS],
+	// how many bytes are on the free lists
+	free: usize,
+}
+
+// the lists only ever point into the heap the allocator was given
+unsafe impl Send for BuddyAllocator {}
+
+impl BuddyAllocator {
+	/// new() is the constructor for BuddyAllocator. it has no memory to hand
+	/// out until init() gives it some.
+	pub const fn new() -> BuddyAllocator {
+		BuddyAllocator {
+			base: 0,
+			free_lists: [ptr::null_mut(); ORDERS],
+			free: 0,
+		}
+	}
+
+	/// order() returns the order of the smallest block that fits a layout
+	fn order(layout: &Layout) -> usize {
+		let size = layout.size().max(layout.align()).max(1 << MIN_ORDER);
+		size.next_power_of_two().trailing_zeros() as usize
+	}
+
+	/// push() puts a block on its order's free list
+	unsafe fn push(&mut self, order: usize, addr: usize) {
+		let block = addr as *mut FreeBlock;
+		block.write(FreeBlock { next: self.free_lists[order] });
+		self.free_lists[order] = block;
+	}
+
+	/// pop() takes a block off its order's free list, if there's one there
+	unsafe fn pop(&mut self, order: usize) -> Option<usize> {
+		let block = self.free_lists[order];
+		if block.is_null() {
+			return None;
+		}
+		self.free_lists[order] = (*block).next;
+		Some(block as usize)
+	}
+
+	/// remove() takes a particular block off its order's free list, and
+	/// returns whether it was there
+	unsafe fn remove(&mut self, order: usize, addr: usize) -> bool {
+		let mut link = &mut self.free_lists[order] as *mut *mut FreeBlock;
+		while !(*link).is_null() {
+			if *link as usize == addr {
+				*link = (**link).next;
+				return true;
+			}
+			link = &mut (**link).next;
+		}
+		false
+	}
+}
+
+impl HeapAllocator for BuddyAllocator {
+	const NAME: &'static str = "buddy";
+
+	/// init() gives the allocator start..start+size to hand out. it's cut
+	/// into the biggest blocks that are aligned to their size, relative to
+	/// start, and whatever's too small for a block is left out.
+	unsafe fn init(&mut self, start: usize, size: usize) {
+		self.base = start;
+		let mut offset = 0;
+		while size - offset >= 1 << MIN_ORDER {
+			let left = size - offset;
+			let order = (MIN_ORDER..ORDERS).rev()
+				.find(|&order| 1 << order <= left
+							&& offset & ((1 << order) - 1) == 0)
+				.unwrap();
+			self.push(order, start + offset);
+			self.free += 1 << order;
+			offset += 1 << order;
+		}
+	}
+
+	/// allocate() takes the smallest free block that's big enough, and
+	/// splits it in half until it's the right size. returns null if nothing
+	/// is big enough, or the heap isn't aligned enough for the layout.
+	fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		if self.base & (layout.align() - 1) != 0 {
+			return ptr::null_mut();
+		}
+		let order = Self::order(&layout);
+		let found = (order..ORDERS).find(|&found|
+			!self.free_lists[found].is_null());
+		let mut found = match found {
+			Some(found) => found,
+			None => return ptr::null_mut(),
+		};
+
+		unsafe {
+			let block = self.pop(found).unwrap();
+			// the top half of each split is the bottom half's buddy
+			while found > order {
+				found -= 1;
+				self.push(found, block + (1 << found));
+			}
+			self.free -= 1 << order;
+			block as *mut u8
+		}
+	}
+
+	/// deallocate() gives a block back, merging it with its buddy for as
+	/// long as the buddy's free too
+	unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+		let mut order = Self::order(&layout);
+		let mut addr = ptr as usize;
+		self.free += 1 << order;
+		while order + 1 < ORDERS {
+			let buddy = self.base + ((addr - self.base) ^ (1 << order));
+			if !self.remove(order, buddy) {
+				break;
+			}
+			addr = addr.min(buddy);
+			order += 1;
+		}
+		self.push(order, addr);
+	}
+
+	/// free_bytes() returns how much is on the free lists
+	fn free_bytes(&self) -> usize {
+		self.free
+	}
+
+	/// largest_free() returns the size of the biggest free block
+	fn largest_free(&self) -> usize {
+		(0..ORDERS).rev()
+			.find(|&order| !self.free_lists[order].is_null())
+			.map_or(0, |order| 1 << order)
+	}
+}
@@ -0,0 +1,74 @@
+// file:	bump.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	A bump allocator. it hands memory out in order and can't reuse any
+//			of it until everything it handed out has been freed.
+
+// includes
+use core::alloc::Layout;
+use core::ptr;
+use super::{align_up, HeapAllocator};
+
+/// BumpAllocator hands out memory by moving a pointer up through the heap.
+/// it's about as fast as allocating gets, and it's fine for boot, where
+/// hardly anything gets freed.
+pub struct BumpAllocator {
+	start: usize,
+	end: usize,
+	// where the next allocation starts looking
+	next: usize,
+	// how many allocations haven't been freed yet
+	live: usize,
+}
+
+impl BumpAllocator {
+	/// new() is the constructor for BumpAllocator. it has no memory to hand
+	/// out until init() gives it some.
+	pub const fn new() -> BumpAllocator {
+		BumpAllocator { start: 0, end: 0, next: 0, live: 0 }
+	}
+}
+
+impl HeapAllocator for BumpAllocator {
+	const NAME: &'static str = "bump";
+
+	/// init() gives the allocator start..start+size to hand out
+	unsafe fn init(&mut self, start: usize, size: usize) {
+		self.start = start;
+		self.end = start + size;
+		self.next = start;
+	}
+
+	/// allocate() hands out the memory right after the last allocation, or
+	/// null if there isn't enough left
+	fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		let start = align_up(self.next, layout.align());
+		match start.checked_add(layout.size()) {
+			Some(end) if end <= self.end => {
+				self.next = end;
+				self.live += 1;
+				start as *mut u8
+			},
+			_ => ptr::null_mut(),
+		}
+	}
+
+	/// deallocate() only keeps count. once the last allocation is freed, the
+	/// whole heap is free again.
+	unsafe fn deallocate(&mut self, _ptr: *mut u8, _layout: Layout) {
+		self.live -= 1;
+		if self.live == 0 {
+			self.next = self.start;
+		}
+	}
+
+	/// free_bytes() returns how much is left past the last allocation
+	fn free_bytes(&self) -> usize {
+		self.end - self.next
+	}
+
+	/// largest_free() is the same as free_bytes(), since it's all one piece
+	fn largest_free(&self) -> usize {
+		self.free_bytes()
+	}
+}
@@ -0,0 +1,119 @@
+// file:	fixed_block.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	A fixed-size block allocator. small allocations are rounded up to
+//			a handful of block sizes, each with its own free list, and
+//			anything bigger goes to a linked list allocator.
+
+// includes
+use core::alloc::Layout;
+use core::ptr;
+use super::HeapAllocator;
+use super::linked_list::LinkedListAllocator;
+
+// how many block sizes there are
+const CLASSES: usize = 8;
+// the block sizes. each block is aligned to its size, so they have to be
+// powers of two, and the smallest has to be as big as the linked list
+// allocator's smallest block, or freed blocks wouldn't add up right.
+const BLOCK_SIZES: [usize; CLASSES] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+// FreeNode is written at the start of every block on a free list
+struct FreeNode {
+	next: *mut FreeNode,
+}
+
+/// FixedBlockAllocator hands out small allocations from per-size free
+/// lists, so allocating and freeing them is a push or a pop
+pub struct FixedBlockAllocator {
+	// the free list for each block size
+	heads: [*mut FreeNode; CLASSES],
+	// where new blocks, and anything too big for one, come from
+	fallback: LinkedListAllocator,
+}
+
+// the lists only ever point into the heap the allocator was given
+unsafe impl Send for FixedBlockAllocator {}
+
+impl FixedBlockAllocator {
+	/// new() is the constructor for FixedBlockAllocator. it has no memory to
+	/// hand out until init() gives it some.
+	pub const fn new() -> FixedBlockAllocator {
+		FixedBlockAllocator {
+			heads: [ptr::null_mut(); CLASSES],
+			fallback: LinkedListAllocator::new(),
+		}
+	}
+
+	/// class() returns which block size a layout fits in, if any
+	fn class(layout: &Layout) -> Option<usize> {
+		let size = layout.size().max(layout.align());
+		BLOCK_SIZES.iter().position(|&block_size| block_size >= size)
+	}
+}
+
+impl HeapAllocator for FixedBlockAllocator {
+	const NAME: &'static str = "fixed block";
+
+	/// init() gives the allocator start..start+size to hand out. it all
+	/// starts out with the fallback, and becomes blocks as they're needed.
+	unsafe fn init(&mut self, start: usize, size: usize) {
+		self.fallback.init(start, size);
+	}
+
+	/// allocate() pops a block off the right free list, or gets a new one
+	/// from the fallback if the list's empty. big allocations go straight
+	/// to the fallback.
+	fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		let class = match Self::class(&layout) {
+			Some(class) => class,
+			None => return self.fallback.allocate(layout),
+		};
+		let head = self.heads[class];
+		if !head.is_null() {
+			self.heads[class] = unsafe { (*head).next };
+			return head as *mut u8;
+		}
+		let block_size = BLOCK_SIZES[class];
+		let layout = unsafe {
+			Layout::from_size_align_unchecked(block_size, block_size)
+		};
+		self.fallback.allocate(layout)
+	}
+
+	/// deallocate() pushes a block onto its free list. blocks never go back
+	/// to the fallback, so they're there for the next allocation that size.
+	unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+		match Self::class(&layout) {
+			Some(class) => {
+				let node = ptr as *mut FreeNode;
+				node.write(FreeNode { next: self.heads[class] });
+				self.heads[class] = node;
+			},
+			None => self.fallback.deallocate(ptr, layout),
+		}
+	}
+
+	/// free_bytes() adds up the blocks on the free lists and whatever the
+	/// fallback has left
+	fn free_bytes(&self) -> usize {
+		let mut total = self.fallback.free_bytes();
+		for (class, &head) in self.heads.iter().enumerate() {
+			let mut node = head;
+			while !node.is_null() {
+				total += BLOCK_SIZES[class];
+				node = unsafe { (*node).next };
+			}
+		}
+		total
+	}
+
+	/// largest_free() returns the biggest thing that could be handed out
+	/// without splitting, either a free block or the fallback's biggest
+	fn largest_free(&self) -> usize {
+		let block = (0..CLASSES).rev()
+			.find(|&class| !self.heads[class].is_null())
+			.map_or(0, |class| BLOCK_SIZES[class]);
+		block.max(self.fallback.largest_free())
+	}
+}
@@ -0,0 +1,173 @@
+// file:	linked_list.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	A first-fit free list allocator. the free blocks are kept in
+//			address order, so freeing a block can merge it with its neighbors.
+
+// includes
+use core::alloc::Layout;
+use core::mem;
+use core::ptr;
+use super::{align_up, HeapAllocator};
+
+// FreeBlock is the header written at the start of every free block
+struct FreeBlock {
+	size: usize,
+	next: *mut FreeBlock,
+}
+
+// the smallest block there can be, since a free one has to hold a header
+const MIN_BLOCK: usize = mem::size_of::<FreeBlock>();
+
+/// LinkedListAllocator hands out memory from a list of free blocks
+pub struct LinkedListAllocator {
+	// a dummy block, whose next is the first real one
+	head: FreeBlock,
+}
+
+// the list only ever points into the heap the allocator was given
+unsafe impl Send for LinkedListAllocator {}
+
+impl LinkedListAllocator {
+	/// new() is the constructor for LinkedListAllocator. it has no memory to
+	/// hand out until init() gives it some.
+	pub const fn new() -> LinkedListAllocator {
+		LinkedListAllocator {
+			head: FreeBlock { size: 0, next: ptr::null_mut() },
+		}
+	}
+
+	/// block_size() returns how big the block for a layout is. every block
+	/// has to be able to hold a header once it's freed.
+	fn block_size(layout: &Layout) -> usize {
+		align_up(layout.size(), mem::align_of::<FreeBlock>()).max(MIN_BLOCK)
+	}
+
+	/// fit() returns where in a free block an allocation would go, if it
+	/// fits at all. the space left on either side has to be big enough to
+	/// become a free block of its own, or not be there at all.
+	fn fit(block: usize, block_size: usize, size: usize, align: usize)
+		-> Option<usize>
+	{
+		let block_end = block + block_size;
+		let mut start = align_up(block, align);
+		if start != block && start - block < MIN_BLOCK {
+			start = align_up(block + MIN_BLOCK, align);
+		}
+		let end = start.checked_add(size)?;
+		if end > block_end {
+			return None;
+		}
+		let after = block_end - end;
+		if after != 0 && after < MIN_BLOCK {
+			return None;
+		}
+		Some(start)
+	}
+
+	/// free_region() adds start..start+size to the list, in address order
+	unsafe fn free_region(&mut self, start: usize, size: usize) {
+		// find the last free block before start
+		let mut previous = &mut self.head as *mut FreeBlock;
+		while !(*previous).next.is_null() && ((*previous).next as usize) < start
+		{
+			previous = (*previous).next;
+		}
+		let next = (*previous).next;
+
+		// merge with the block after, then the one before, when they touch
+		let block = start as *mut FreeBlock;
+		let mut size = size;
+		let mut after = next;
+		if !next.is_null() && start + size == next as usize {
+			size += (*next).size;
+			after = (*next).next;
+		}
+		if previous != &mut self.head as *mut FreeBlock
+			&& previous as usize + (*previous).size == start
+		{
+			(*previous).size += size;
+			(*previous).next = after;
+		} else {
+			block.write(FreeBlock { size, next: after });
+			(*previous).next = block;
+		}
+	}
+}
+
+impl HeapAllocator for LinkedListAllocator {
+	const NAME: &'static str = "linked list";
+
+	/// init() gives the allocator start..start+size to hand out
+	unsafe fn init(&mut self, start: usize, size: usize) {
+		self.free_region(start, size);
+	}
+
+	/// allocate() finds the first free block the layout fits in and carves
+	/// the allocation out of it. whatever's left on either side goes back
+	/// on the list. returns null if nothing fits.
+	fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		let size = Self::block_size(&layout);
+		let align = layout.align().max(mem::align_of::<FreeBlock>());
+
+		let mut previous = &mut self.head as *mut FreeBlock;
+		unsafe {
+			while !(*previous).next.is_null() {
+				let block = (*previous).next;
+				let block_size = (*block).size;
+				let start = match Self::fit(block as usize, block_size, size,
+											align) {
+					Some(start) => start,
+					None => {
+						previous = block;
+						continue;
+					},
+				};
+
+				// unlink the block, then put back whatever's left over
+				(*previous).next = (*block).next;
+				let block_end = block as usize + block_size;
+				if start != block as usize {
+					self.free_region(block as usize, start - block as usize);
+				}
+				if start + size != block_end {
+					self.free_region(start + size, block_end - start - size);
+				}
+				return start as *mut u8;
+			}
+		}
+		ptr::null_mut()
+	}
+
+	/// deallocate() puts a block back on the list, merging it with the
+	/// blocks on either side if they're free too
+	unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+		self.free_region(ptr as usize, Self::block_size(&layout));
+	}
+
+	/// free_bytes() adds up every free block
+	fn free_bytes(&self) -> usize {
+		let mut total = 0;
+		let mut block = self.head.next;
+		while !block.is_null() {
+			unsafe {
+				total += (*block).size;
+				block = (*block).next;
+			}
+		}
+		total
+	}
+
+	/// largest_free() returns the size of the biggest free block
+	fn largest_free(&self) -> usize {
+		let mut largest = 0;
+		let mut block = self.head.next;
+		while !block.is_null() {
+			unsafe {
+				largest = largest.max((*block).size);
+				block = (*block).next;
+			}
+		}
+		largest
+	}
+}
@@ -0,0 +1,264 @@
+// file:	mod.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	The kernel heap, and the global allocator that hands it out to
+//			the alloc crate. which allocator design it uses is picked with
+//			the heap-* cargo features. slab caches for fixed-size objects
+//			live in slab.rs.
+
+// includes
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use crate::memory::paging::{self, PagingError};
+use crate::sync::IrqSpinlock;
+
+// declare the submodules
+pub mod buddy;
+pub mod bump;
+pub mod fixed_block;
+pub mod linked_list;
+pub mod slab;
+
+// includes from the submodules
+pub use self::buddy::BuddyAllocator;
+pub use self::bump::BumpAllocator;
+pub use self::fixed_block::FixedBlockAllocator;
+pub use self::linked_list::LinkedListAllocator;
+
+// the global allocator is whichever heap-* feature is on, or the linked list
+// allocator if none of them are
+#[cfg(any(
+	all(feature = "heap-bump", any(feature = "heap-linked-list",
+									feature = "heap-fixed-block",
+									feature = "heap-buddy")),
+	all(feature = "heap-linked-list", any(feature = "heap-fixed-block",
+											feature = "heap-buddy")),
+	all(feature = "heap-fixed-block", feature = "heap-buddy")))]
+compile_error!("only one of the heap-* features can be enabled at a time");
+
+#[cfg(feature = "heap-bump")]
+type Selected = BumpAllocator;
+#[cfg(feature = "heap-fixed-block")]
+type Selected = FixedBlockAllocator;
+#[cfg(feature = "heap-buddy")]
+type Selected = BuddyAllocator;
+#[cfg(not(any(feature = "heap-bump", feature = "heap-fixed-block",
+				feature = "heap-buddy")))]
+type Selected = LinkedListAllocator;
+
+/// where the heap starts. it's the 512GiB under p4 entry 509, right below
+/// the mmio window.
+pub const HEAP_START: u64 = 0xffff_fe80_0000_0000;
+/// how much heap gets mapped at boot
+pub const HEAP_SIZE: u64 = 4 * 1024 * 1024;
+
+/// align_up() rounds addr up to a multiple of align, which has to be a power
+/// of two
+fn align_up(addr: usize, align: usize) -> usize {
+	(addr + align - 1) & !(align - 1)
+}
+
+/// HeapAllocator is what every allocator design implements. each one is
+/// given a single region of memory by init(), and hands out pieces of it.
+pub trait HeapAllocator {
+	/// the design's name, for stats and the benchmark
+	const NAME: &'static str;
+
+	/// init() gives the allocator start..start+size to hand out. it has to
+	/// be unused memory that stays mapped for as long as the allocator
+	/// lives, and init() can only be called once.
+	unsafe fn init(&mut self, start: usize, size: usize);
+
+	/// allocate() returns memory that fits layout, or null if there isn't
+	/// any
+	fn allocate(&mut self, layout: Layout) -> *mut u8;
+
+	/// deallocate() gives back memory allocate() handed out for layout
+	unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout);
+
+	/// free_bytes() returns how many bytes could still be handed out
+	fn free_bytes(&self) -> usize;
+
+	/// largest_free() returns the biggest piece of free memory there is,
+	/// which is about the biggest allocation that would work
+	fn largest_free(&self) -> usize;
+}
+
+/// HeapStats is a snapshot of how a heap is being used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+	// which allocator design it is
+	pub name: &'static str,
+	// how big the heap is
+	pub size: usize,
+	// how much of it is handed out, including what the allocator wastes
+	pub used: usize,
+	// how much of it is free
+	pub free: usize,
+	// how much the live allocations asked for
+	pub requested: usize,
+	// the biggest piece of free memory
+	pub largest_free: usize,
+	// how many allocations and frees there have been
+	pub allocations: u64,
+	pub deallocations: u64,
+	// how many allocations couldn't be satisfied
+	pub failures: u64,
+}
+
+impl HeapStats {
+	/// fragmentation() returns how much of the free memory isn't part of the
+	/// biggest free piece, in percent. 0 means it's all in one piece.
+	pub fn fragmentation(&self) -> usize {
+		if self.free == 0 {
+			0
+		} else {
+			100 - self.largest_free * 100 / self.free
+		}
+	}
+}
+
+impl fmt::Display for HeapStats {
+	/// fmt() prints the stats on a line
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {} of {} bytes used ({} requested), {} free, {}% \
+				fragmented, {} allocs, {} frees, {} failed", self.name,
+				self.used, self.size, self.requested, self.free,
+				self.fragmentation(), self.allocations, self.deallocations,
+				self.failures)
+	}
+}
+
+/// Heap wraps an allocator design, and keeps the counts for its stats
+pub struct Heap<A> {
+	allocator: A,
+	size: usize,
+	requested: usize,
+	allocations: u64,
+	deallocations: u64,
+	failures: u64,
+}
+
+impl<A> Heap<A> {
+	/// new() is the constructor for Heap
+	pub const fn new(allocator: A) -> Heap<A> {
+		Heap {
+			allocator,
+			size: 0,
+			requested: 0,
+			allocations: 0,
+			deallocations: 0,
+			failures: 0,
+		}
+	}
+}
+
+impl<A: HeapAllocator> Heap<A> {
+	/// init() gives the heap start..start+size. see HeapAllocator::init().
+	pub unsafe fn init(&mut self, start: usize, size: usize) {
+		self.size = size;
+		self.allocator.init(start, size);
+	}
+
+	/// allocate() allocates, and counts it
+	pub fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		let ptr = self.allocator.allocate(layout);
+		if ptr.is_null() {
+			self.failures += 1;
+		} else {
+			self.allocations += 1;
+			self.requested += layout.size();
+		}
+		ptr
+	}
+
+	/// deallocate() frees, and counts it
+	pub unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+		self.allocator.deallocate(ptr, layout);
+		self.deallocations += 1;
+		self.requested -= layout.size();
+	}
+
+	/// stats() returns a snapshot of the heap's stats
+	pub fn stats(&self) -> HeapStats {
+		let free = self.allocator.free_bytes();
+		HeapStats {
+			name: A::NAME,
+			size: self.size,
+			used: self.size - free,
+			free,
+			requested: self.requested,
+			largest_free: self.allocator.largest_free(),
+			allocations: self.allocations,
+			deallocations: self.deallocations,
+			failures: self.failures,
+		}
+	}
+}
+
+/// Locked wraps an allocator in an IrqSpinlock, so it can be the global
+/// allocator, and be used from interrupt handlers
+pub struct Locked<A> {
+	inner: IrqSpinlock<A>,
+}
+
+impl<A> Locked<A> {
+	/// new() is the constructor for Locked
+	pub const fn new(inner: A) -> Locked<A> {
+		Locked { inner: IrqSpinlock::new(inner) }
+	}
+}
+
+unsafe impl<A: HeapAllocator> GlobalAlloc for Locked<Heap<A>> {
+	/// alloc() hands out a block, or null if the heap's out of room
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		self.inner.lock().allocate(layout)
+	}
+
+	/// dealloc() gives a block back
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		self.inner.lock().deallocate(ptr, layout);
+	}
+}
+
+// the allocator everything in alloc goes through. not when testing on the
+// host, since std brings its own and the heap isn't mapped there.
+#[cfg_attr(not(test), global_allocator)]
+static ALLOCATOR: Locked<Heap<Selected>> =
+	Locked::new(Heap::new(Selected::new()));
+
+/// init_heap() maps the heap with fresh frames, and gives it to the global
+/// allocator. it has to run after the frame allocator's up, and only once.
+pub fn init_heap() -> Result<(), PagingError> {
+	let first: Page = Page::containing_address(VirtAddr::new(HEAP_START));
+	let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+	for i in 0..HEAP_SIZE / 4096 {
+		unsafe { paging::map_new(first + i, flags)? };
+	}
+
+	unsafe {
+		ALLOCATOR.inner.lock().init(HEAP_START as usize, HEAP_SIZE as usize);
+	}
+	Ok(())
+}
+
+/// heap_free() returns how many bytes of the heap are free
+pub fn heap_free() -> usize {
+	ALLOCATOR.inner.lock().allocator.free_bytes()
+}
+
+/// heap_stats() returns a snapshot of the kernel heap's stats
+pub fn heap_stats() -> HeapStats {
+	ALLOCATOR.inner.lock().stats()
+}
+
+/// alloc_error() is called when an allocation fails. there's no recovering
+/// from that, so it says what couldn't be allocated and panics.
+#[cfg(not(test))]
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+	panic!("out of heap: couldn't allocate {} bytes aligned to {} \
+			({} bytes free)", layout.size(), layout.align(), heap_free());
+}
@@ -0,0 +1,507 @@
+// file:	slab.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Slab caches, for kernel objects that are all the same size and get
+//			allocated and freed all the time. each named cache carves slabs of
+//			one or more pages into objects, and hands the pages back to the
+//			frame allocator once a slab's empty and reclaimed.
+
+// includes
+use core::mem;
+use core::ptr::{self, NonNull};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use crate::memory::{self, paging};
+use crate::sync::IrqSpinlock;
+use super::align_up;
+
+/// the most caches there can be
+pub const MAX_CACHES: usize = 32;
+
+// where slabs get mapped. it's the 512GiB under p4 entry 508, right below
+// the heap.
+const WINDOW_START: u64 = 0xffff_fe00_0000_0000;
+// how many pages of that we hand out, which is 64MiB
+const WINDOW_PAGES: usize = 16384;
+// the most pages one slab can be
+const MAX_SLAB_PAGES: usize = 8;
+// how many objects a slab should hold, if it can without going past that
+const MIN_OBJECTS: usize = 8;
+const PAGE_SIZE: usize = 4096;
+// the end of a slab's free list
+const NO_OBJECT: u16 = !0;
+
+/// SlabError is why a cache couldn't be created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlabError {
+	// all MAX_CACHES caches are taken
+	TooManyCaches,
+	// there's already a cache with that name
+	NameTaken,
+	// the objects wouldn't fit in the biggest slab there can be
+	ObjectTooBig,
+	// the alignment isn't a power of two, or is bigger than a page
+	BadAlignment,
+}
+
+/// Constructor sets up a fresh object. it's run on every object in a slab
+/// when the slab is created, not on every allocation, so objects have to be
+/// freed back in their constructed state. it runs with the caches locked,
+/// so it can't touch the slab allocator itself.
+pub type Constructor = fn(*mut u8);
+
+// Slab is the header at the start of every slab. right after it is the
+// slab's free list, a u16 per object holding the index of the next free one,
+// and the objects come after that. the list lives out here so that objects
+// are never written to while they're free, which is what lets them keep
+// their constructed state.
+struct Slab {
+	// which cache the slab belongs to
+	cache: usize,
+	// the slab's neighbors on whichever list it's on
+	prev: *mut Slab,
+	next: *mut Slab,
+	// the index of the slab's first free object, or NO_OBJECT
+	free: u16,
+	// how many of its objects are handed out
+	in_use: usize,
+}
+
+// Cache is everything about a cache. it's all plain data, so the table of
+// them can be built in a const.
+#[derive(Clone, Copy)]
+struct Cache {
+	name: &'static str,
+	// the size of an object, rounded up to its alignment
+	object_size: usize,
+	slab_pages: usize,
+	objects_per_slab: usize,
+	// where the first object is, from the start of a slab, which is past
+	// the header and the free list
+	first_object: usize,
+	constructor: Option<Constructor>,
+	// slabs with some objects handed out, all of them, and none of them
+	partial: *mut Slab,
+	full: *mut Slab,
+	empty: *mut Slab,
+	// how many objects are handed out
+	active: usize,
+	// how many slabs there are
+	slabs: usize,
+	allocations: u64,
+	frees: u64,
+}
+
+// Slabs is the cache table, and which pages of the window slabs are using
+struct Slabs {
+	caches: [Option<Cache>; MAX_CACHES],
+	// a bit per page of the window, set if it's part of a slab
+	window: [u64; WINDOW_PAGES / 64],
+}
+
+// the lists only ever point into slabs, which stay mapped while they're on
+// them
+unsafe impl Send for Slabs {}
+
+// the cache table. it's an IrqSpinlock, so objects can be allocated and
+// freed from interrupt handlers.
+static SLABS: IrqSpinlock<Slabs> = IrqSpinlock::new(Slabs {
+	caches: [None; MAX_CACHES],
+	window: [0; WINDOW_PAGES / 64],
+});
+
+/// SlabCache is a handle to a cache, which is how objects are allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlabCache(usize);
+
+/// CacheStats is a snapshot of a cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+	pub name: &'static str,
+	pub object_size: usize,
+	pub objects_per_slab: usize,
+	pub slab_pages: usize,
+	// how many objects are handed out, and how many there are
+	pub active_objects: usize,
+	pub total_objects: usize,
+	// how many slabs are on each list
+	pub partial_slabs: usize,
+	pub full_slabs: usize,
+	pub empty_slabs: usize,
+	// how many allocations and frees there have been
+	pub allocations: u64,
+	pub frees: u64,
+}
+
+/// next_free() returns where the slab keeps the index of the free object
+/// after object
+unsafe fn next_free(slab: *mut Slab, object: usize) -> *mut u16 {
+	(slab.add(1) as *mut u16).add(object)
+}
+
+/// list_len() counts the slabs on a list
+unsafe fn list_len(mut slab: *mut Slab) -> usize {
+	let mut len = 0;
+	while !slab.is_null() {
+		len += 1;
+		slab = (*slab).next;
+	}
+	len
+}
+
+impl Cache {
+	/// slab_bytes() returns how big each of the cache's slabs is
+	fn slab_bytes(&self) -> usize {
+		self.slab_pages * PAGE_SIZE
+	}
+
+	/// list() returns the list a slab with in_use objects handed out goes on
+	fn list(&mut self, in_use: usize) -> &mut *mut Slab {
+		if in_use == 0 {
+			&mut self.empty
+		} else if in_use == self.objects_per_slab {
+			&mut self.full
+		} else {
+			&mut self.partial
+		}
+	}
+
+	/// push() puts a slab at the front of the list it belongs on
+	unsafe fn push(&mut self, slab: *mut Slab) {
+		let head = self.list((*slab).in_use);
+		(*slab).prev = ptr::null_mut();
+		(*slab).next = *head;
+		if !(*head).is_null() {
+			(**head).prev = slab;
+		}
+		*head = slab;
+	}
+
+	/// unlink() takes a slab off the list it's on
+	unsafe fn unlink(&mut self, slab: *mut Slab) {
+		if !(*slab).next.is_null() {
+			(*(*slab).next).prev = (*slab).prev;
+		}
+		if (*slab).prev.is_null() {
+			*self.list((*slab).in_use) = (*slab).next;
+		} else {
+			(*(*slab).prev).next = (*slab).next;
+		}
+	}
+
+	/// stats() returns a snapshot of the cache
+	fn stats(&self) -> CacheStats {
+		unsafe {
+			CacheStats {
+				name: self.name,
+				object_size: self.object_size,
+				objects_per_slab: self.objects_per_slab,
+				slab_pages: self.slab_pages,
+				active_objects: self.active,
+				total_objects: self.slabs * self.objects_per_slab,
+				partial_slabs: list_len(self.partial),
+				full_slabs: list_len(self.full),
+				empty_slabs: list_len(self.empty),
+				allocations: self.allocations,
+				frees: self.frees,
+			}
+		}
+	}
+}
+
+impl Slabs {
+	/// cache() returns a cache, which has to exist
+	fn cache(&mut self, index: usize) -> &mut Cache {
+		self.caches[index].as_mut().expect("no such slab cache")
+	}
+
+	/// is_used() returns whether a page of the window is part of a slab
+	fn is_used(&self, page: usize) -> bool {
+		self.window[page / 64] & (1 << (page % 64)) != 0
+	}
+
+	/// set_used() marks pages of the window as used or not
+	fn set_used(&mut self, first: usize, count: usize, used: bool) {
+		for page in first..first + count {
+			if used {
+				self.window[page / 64] |= 1 << (page % 64);
+			} else {
+				self.window[page / 64] &= !(1 << (page % 64));
+			}
+		}
+	}
+
+	/// grow() makes a new slab for a cache, and puts it on its empty list.
+	/// slabs are aligned to their size, so an object's slab can be found by
+	/// rounding its address down. returns false if there was no room in the
+	/// window, or no frames left.
+	fn grow(&mut self, index: usize) -> bool {
+		let pages = self.cache(index).slab_pages;
+		let first = match (0..WINDOW_PAGES).step_by(pages)
+			.find(|&first| (first..first + pages).all(|page|
+				!self.is_used(page)))
+		{
+			Some(first) => first,
+			None => return false,
+		};
+
+		let start = Page::containing_address(VirtAddr::new(WINDOW_START))
+			+ first as u64;
+		let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+		for i in 0..pages {
+			if unsafe { paging::map_new(start + i as u64, flags) }.is_err() {
+				unsafe { unmap_pages(start, i) };
+				return false;
+			}
+		}
+		self.set_used(first, pages, true);
+
+		// construct every object, and put them all on the slab's free list
+		// in order
+		let cache = self.cache(index);
+		let base = start.start_address().as_u64() as usize;
+		let slab = base as *mut Slab;
+		for i in 0..cache.objects_per_slab {
+			let object = base + cache.first_object + i * cache.object_size;
+			if let Some(constructor) = cache.constructor {
+				constructor(object as *mut u8);
+			}
+			let next = if i + 1 == cache.objects_per_slab {
+				NO_OBJECT
+			} else {
+				(i + 1) as u16
+			};
+			unsafe { next_free(slab, i).write(next) };
+		}
+		unsafe {
+			slab.write(Slab {
+				cache: index,
+				prev: ptr::null_mut(),
+				next: ptr::null_mut(),
+				free: 0,
+				in_use: 0,
+			});
+			cache.push(slab);
+		}
+		cache.slabs += 1;
+		true
+	}
+
+	/// shrink() gives every empty slab a cache has back to the frame
+	/// allocator, and returns how many pages that was
+	fn shrink(&mut self, index: usize) -> usize {
+		let mut pages = 0;
+		loop {
+			let cache = self.cache(index);
+			let slab = cache.empty;
+			if slab.is_null() {
+				return pages;
+			}
+			let slab_pages = cache.slab_pages;
+			unsafe { cache.unlink(slab) };
+			cache.slabs -= 1;
+
+			let start = Page::containing_address(VirtAddr::new(slab as u64));
+			let first = (slab as u64 - WINDOW_START) as usize / PAGE_SIZE;
+			unsafe { unmap_pages(start, slab_pages) };
+			self.set_used(first, slab_pages, false);
+			pages += slab_pages;
+		}
+	}
+}
+
+/// unmap_pages() unmaps count pages starting at start, and frees their
+/// frames
+unsafe fn unmap_pages(start: Page, count: usize) {
+	for i in 0..count {
+		let frame = paging::unmap(start + i as u64)
+			.expect("slab page wasn't mapped");
+		memory::free_frame(frame);
+	}
+}
+
+/// create_cache() makes a new cache for objects of size bytes aligned to
+/// align. if there's a constructor, it's run on every object as its slab is
+/// created.
+pub fn create_cache(name: &'static str, size: usize, align: usize,
+					constructor: Option<Constructor>)
+	-> Result<SlabCache, SlabError>
+{
+	if !align.is_power_of_two() || align > PAGE_SIZE {
+		return Err(SlabError::BadAlignment);
+	}
+
+	let object_size = align_up(size.max(1), align);
+
+	// the header and free list take a slab's first first_object(count)
+	// bytes, and the objects the rest
+	let first_object = |count: usize| {
+		align_up(mem::size_of::<Slab>() + count * mem::size_of::<u16>(),
+					align)
+	};
+	// the most objects a slab of pages pages holds. the free list's
+	// alignment padding can cost an object or two off the first guess.
+	let objects = |pages: usize| {
+		let bytes = pages * PAGE_SIZE;
+		let mut count = bytes.saturating_sub(mem::size_of::<Slab>())
+			/ (object_size + mem::size_of::<u16>());
+		while count > 0 && first_object(count) + count * object_size > bytes {
+			count -= 1;
+		}
+		count
+	};
+
+	// the smallest slab that fits enough objects, or the biggest there is
+	let mut slab_pages = 1;
+	while objects(slab_pages) < MIN_OBJECTS && slab_pages < MAX_SLAB_PAGES {
+		slab_pages *= 2;
+	}
+	let objects_per_slab = objects(slab_pages);
+	if objects_per_slab == 0 {
+		return Err(SlabError::ObjectTooBig);
+	}
+
+	let mut slabs = SLABS.lock();
+	if slabs.caches.iter().flatten().any(|cache| cache.name == name) {
+		return Err(SlabError::NameTaken);
+	}
+	let index = slabs.caches.iter().position(Option::is_none)
+		.ok_or(SlabError::TooManyCaches)?;
+	slabs.caches[index] = Some(Cache {
+		name,
+		object_size,
+		slab_pages,
+		objects_per_slab,
+		first_object: first_object(objects_per_slab),
+		constructor,
+		partial: ptr::null_mut(),
+		full: ptr::null_mut(),
+		empty: ptr::null_mut(),
+		active: 0,
+		slabs: 0,
+		allocations: 0,
+		frees: 0,
+	});
+	Ok(SlabCache(index))
+}
+
+/// find_cache() returns the cache called name, if there is one
+pub fn find_cache(name: &str) -> Option<SlabCache> {
+	SLABS.lock().caches.iter()
+		.position(|cache| cache.map_or(false, |cache| cache.name == name))
+		.map(SlabCache)
+}
+
+impl SlabCache {
+	/// alloc() hands out an object, from a partly used slab if there is one,
+	/// then an empty one, then a brand new one. returns None if a new slab
+	/// was needed and couldn't be made.
+	pub fn alloc(self) -> Option<NonNull<u8>> {
+		let mut slabs = SLABS.lock();
+		let needs_slab = {
+			let cache = slabs.cache(self.0);
+			cache.partial.is_null() && cache.empty.is_null()
+		};
+		if needs_slab && !slabs.grow(self.0) {
+			return None;
+		}
+
+		let cache = slabs.cache(self.0);
+		let slab = if cache.partial.is_null() {
+			cache.empty
+		} else {
+			cache.partial
+		};
+		unsafe {
+			cache.unlink(slab);
+			let object = (*slab).free as usize;
+			(*slab).free = *next_free(slab, object);
+			(*slab).in_use += 1;
+			cache.push(slab);
+			cache.active += 1;
+			cache.allocations += 1;
+			NonNull::new((slab as usize + cache.first_object
+							+ object * cache.object_size) as *mut u8)
+		}
+	}
+
+	/// free() gives an object back to the slab it came from. it has to have
+	/// come from this cache's alloc(), and be back in its constructed state
+	/// if the cache has a constructor.
+	pub unsafe fn free(self, object: NonNull<u8>) {
+		let mut slabs = SLABS.lock();
+		let cache = slabs.cache(self.0);
+		let address = object.as_ptr() as usize;
+		let slab = (address & !(cache.slab_bytes() - 1)) as *mut Slab;
+		let offset = (address - slab as usize).wrapping_sub(cache.first_object);
+		assert!(address as u64 >= WINDOW_START && (*slab).cache == self.0
+				&& offset % cache.object_size == 0
+				&& offset / cache.object_size < cache.objects_per_slab,
+				"freed {:#x} to slab cache {}, which it isn't from", address,
+				cache.name);
+
+		cache.unlink(slab);
+		let index = offset / cache.object_size;
+		*next_free(slab, index) = (*slab).free;
+		(*slab).free = index as u16;
+		(*slab).in_use -= 1;
+		cache.push(slab);
+		cache.active -= 1;
+		cache.frees += 1;
+	}
+
+	/// shrink() gives the cache's empty slabs back to the frame allocator,
+	/// and returns how many pages that was
+	pub fn shrink(self) -> usize {
+		SLABS.lock().shrink(self.0)
+	}
+
+	/// name() returns what the cache is called
+	pub fn name(self) -> &'static str {
+		SLABS.lock().cache(self.0).name
+	}
+
+	/// stats() returns a snapshot of the cache
+	pub fn stats(self) -> CacheStats {
+		SLABS.lock().cache(self.0).stats()
+	}
+}
+
+/// reclaim() gives every cache's empty slabs back to the frame allocator,
+/// and returns how many pages that was
+pub fn reclaim() -> usize {
+	let mut slabs = SLABS.lock();
+	let mut pages = 0;
+	for index in 0..MAX_CACHES {
+		if slabs.caches[index].is_some() {
+			pages += slabs.shrink(index);
+		}
+	}
+	pages
+}
+
+/// dump_slabinfo() prints every cache over serial, laid out like
+/// /proc/slabinfo
+pub fn dump_slabinfo() {
+	// copy the stats out first, so we're not printing with the caches locked
+	let mut stats = [None; MAX_CACHES];
+	{
+		let slabs = SLABS.lock();
+		for (slot, cache) in stats.iter_mut().zip(slabs.caches.iter()) {
+			*slot = cache.map(|cache| cache.stats());
+		}
+	}
+
+	crate::serial_println!("slabinfo - version: 2.1");
+	crate::serial_println!("# name                 <active_objs> <num_objs> \
+							<objsize> <objperslab> <pagesperslab> : slabdata \
+							<partial> <full> <empty>");
+	for stats in stats.iter().flatten() {
+		crate::serial_println!("{:<22} {:>13} {:>10} {:>9} {:>12} {:>14} : \
+								slabdata {:>9} {:>6} {:>7}", stats.name,
+								stats.active_objects, stats.total_objects,
+								stats.object_size, stats.objects_per_slab,
+								stats.slab_pages, stats.partial_slabs,
+								stats.full_slabs, stats.empty_slabs);
+	}
+}
@@ -0,0 +1,166 @@
+// file:	mod.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Architecture abstraction. code that isn't arch-specific talks to the
+//			cpu through these traits, and Arch is whichever implementation we
+//			are building for.
+
+// includes
+use core::fmt;
+
+// declare the implementations. each one provides a zero-sized Arch type
+// that implements every trait below. the interrupts module picks its IDT
+// backend off of the same target_arch.
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+
+#[cfg(target_arch = "x86_64")]
+pub use self::x86_64::X86_64 as Arch;
+
+// Interrupts is turning interrupt delivery on and off
+pub trait Interrupts {
+	/// enable_interrupts() lets interrupts be delivered
+	fn enable_interrupts();
+
+	/// disable_interrupts() stops interrupts from being delivered
+	fn disable_interrupts();
+
+	/// interrupts_enabled() returns whether interrupts are being delivered
+	fn interrupts_enabled() -> bool;
+
+	/// without_interrupts() runs f with interrupts off, then puts them back
+	/// the way they were
+	fn without_interrupts<F, R>(f: F) -> R
+		where F: FnOnce() -> R
+	{
+		let were_enabled = Self::interrupts_enabled();
+		if were_enabled {
+			Self::disable_interrupts();
+		}
+		let result = f();
+		if were_enabled {
+			Self::enable_interrupts();
+		}
+		result
+	}
+}
+
+// Cpu is controlling the cpu itself
+pub trait Cpu {
+	/// halt() sleeps until the next interrupt
+	fn halt();
+
+	/// halt_forever() stops the cpu for good
+	fn halt_forever() -> ! {
+		loop {
+			Self::halt();
+		}
+	}
+
+	/// exit_emulator() asks the emulator we're running in to exit. it does
+	/// nothing on real hardware.
+	fn exit_emulator();
+
+	/// fmt_system_registers() writes out the registers that control the cpu
+	/// itself, rather than the code it was running, for crash reports
+	fn fmt_system_registers(f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+// PortIo is the separate I/O address space, for the platforms that have one
+pub trait PortIo {
+	/// read_port() reads a byte from an I/O port
+	unsafe fn read_port(port: u16) -> u8;
+
+	/// write_port() writes a byte to an I/O port
+	unsafe fn write_port(port: u16, value: u8);
+}
+
+// Tlb is keeping the cpu's cached translations in step with the page tables
+pub trait Tlb {
+	/// invalidate_page() drops whatever's cached for the page at address
+	fn invalidate_page(address: u64);
+
+	/// invalidate_all() drops every cached translation that isn't global
+	fn invalidate_all();
+}
+
+// Segments is loading segment registers, for the platforms that still have
+// them. the descriptor tables themselves are the platform's business.
+pub trait Segments {
+	/// Selector picks a segment out of the loaded descriptor table
+	type Selector;
+
+	/// load_segments() reloads the code and data segment registers and the
+	/// task register. the descriptor table has to be loaded already.
+	unsafe fn load_segments(code: Self::Selector, data: Self::Selector,
+							task: Self::Selector);
+}
+
+// InterruptController is whatever routes and acknowledges IRQ lines
+pub trait InterruptController {
+	/// line_count() returns how many IRQ lines the controller has
+	fn line_count(&self) -> u8;
+
+	/// enable_line() unmasks an IRQ line
+	fn enable_line(&self, line: u8);
+
+	/// disable_line() masks an IRQ line
+	fn disable_line(&self, line: u8);
+
+	/// end_of_interrupt() acknowledges an IRQ line
+	fn end_of_interrupt(&self, line: u8);
+}
+
+// ContextFrame is the state an interrupt or exception saved, which handlers
+// can look at and change before it gets restored
+pub trait ContextFrame {
+	/// instruction_pointer() returns where execution will resume
+	fn instruction_pointer(&self) -> u64;
+
+	/// set_instruction_pointer() changes where execution will resume
+	fn set_instruction_pointer(&mut self, address: u64);
+
+	/// stack_pointer() returns the interrupted code's stack pointer
+	fn stack_pointer(&self) -> u64;
+
+	/// interrupts_were_enabled() returns whether the interrupted code had
+	/// interrupts on
+	fn interrupts_were_enabled(&self) -> bool;
+
+	/// error_code() returns the exception's error code, or 0 if it has none
+	fn error_code(&self) -> u64;
+
+	/// set_return_value() sets the register a system call returns in
+	fn set_return_value(&mut self, value: u64);
+
+	/// syscall_number() returns the register a system call's number comes
+	/// in
+	fn syscall_number(&self) -> u64;
+
+	/// syscall_args() returns the registers a system call's arguments come
+	/// in, in ABI order
+	fn syscall_args(&self) -> [u64; 6];
+
+	/// fmt_registers() writes out every register the frame saved, for crash
+	/// reports
+	fn fmt_registers(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+// EarlyConsole is the simplest output the platform has. it takes no locks
+// and needs no setup, so it works from boot until after everything else has
+// broken.
+pub trait EarlyConsole {
+	/// early_write() writes bytes to the console, waiting as long as it has to
+	fn early_write(bytes: &[u8]);
+}
+
+// EarlyWriter lets the early console be used with write!
+pub struct EarlyWriter;
+
+impl fmt::Write for EarlyWriter {
+	/// write_str() passes a string straight to the early console
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		Arch::early_write(s.as_bytes());
+		Ok(())
+	}
+}
@@ -0,0 +1,262 @@
+// file:	x86_64.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	The x86_64 implementation of the architecture traits
+
+// includes
+use core::fmt;
+use x86_64::VirtAddr;
+use x86_64::instructions::{self, port::Port, tlb};
+use x86_64::registers::model_specific::Msr;
+use x86_64::registers::rflags::{self, RFlags};
+use x86_64::structures::gdt::SegmentSelector;
+use crate::interrupts::InterruptContext;
+use crate::syscall::SyscallFrame;
+use super::{ContextFrame, Cpu, EarlyConsole, Interrupts, PortIo, Segments,
+			Tlb};
+
+// COM1, and its line status register
+const COM1: u16 = 0x3f8;
+const COM1_LINE_STATUS: u16 = COM1 + 5;
+// line status bit that says the transmit buffer has room
+const LINE_STATUS_THR_EMPTY: u8 = 1 << 5;
+
+// qemu's isa-debug-exit device
+const QEMU_EXIT_PORT: u16 = 0xf4;
+
+// the MSR with the long mode and syscall enables
+const IA32_EFER: u32 = 0xc000_0080;
+
+// X86_64 is the x86_64 architecture
+pub struct X86_64;
+
+impl Interrupts for X86_64 {
+	/// enable_interrupts() sets IF
+	fn enable_interrupts() {
+		instructions::interrupts::enable();
+	}
+
+	/// disable_interrupts() clears IF
+	fn disable_interrupts() {
+		instructions::interrupts::disable();
+	}
+
+	/// interrupts_enabled() checks IF
+	fn interrupts_enabled() -> bool {
+		rflags::read().contains(RFlags::INTERRUPT_FLAG)
+	}
+}
+
+impl Cpu for X86_64 {
+	/// halt() is hlt
+	fn halt() {
+		instructions::hlt();
+	}
+
+	/// exit_emulator() writes to qemu's debug exit port, if it's there
+	fn exit_emulator() {
+		let mut port = Port::<u32>::new(QEMU_EXIT_PORT);
+		unsafe { port.write(0) };
+	}
+
+	/// fmt_system_registers() writes out the control registers and EFER
+	fn fmt_system_registers(f: &mut fmt::Formatter) -> fmt::Result {
+		let (cr0, cr2, cr3, cr4): (u64, u64, u64, u64);
+		unsafe {
+			asm!("mov $0, cr0" : "=r"(cr0) ::: "intel", "volatile");
+			asm!("mov $0, cr2" : "=r"(cr2) ::: "intel", "volatile");
+			asm!("mov $0, cr3" : "=r"(cr3) ::: "intel", "volatile");
+			asm!("mov $0, cr4" : "=r"(cr4) ::: "intel", "volatile");
+		}
+		let efer = unsafe { Msr::new(IA32_EFER).read() };
+
+		writeln!(f, "cr0: {:#018x} cr2: {:#018x} cr3: {:#018x}",
+					cr0, cr2, cr3)?;
+		write!(f, "cr4: {:#018x} efer: {:#018x}", cr4, efer)
+	}
+}
+
+impl PortIo for X86_64 {
+	/// read_port() is in
+	unsafe fn read_port(port: u16) -> u8 {
+		Port::<u8>::new(port).read()
+	}
+
+	/// write_port() is out
+	unsafe fn write_port(port: u16, value: u8) {
+		Port::<u8>::new(port).write(value);
+	}
+}
+
+impl Tlb for X86_64 {
+	/// invalidate_page() is invlpg
+	fn invalidate_page(address: u64) {
+		tlb::flush(VirtAddr::new(address));
+	}
+
+	/// invalidate_all() reloads CR3, which keeps global pages
+	fn invalidate_all() {
+		tlb::flush_all();
+	}
+}
+
+impl Segments for X86_64 {
+	type Selector = SegmentSelector;
+
+	/// load_segments() reloads cs with a far return, ss, ds and es with the
+	/// data segment, and the task register
+	unsafe fn load_segments(code: SegmentSelector, data: SegmentSelector,
+							task: SegmentSelector)
+	{
+		use x86_64::instructions::segmentation::{set_cs, load_ds, load_es,
+													load_ss};
+		use x86_64::instructions::tables::load_tss;
+
+		set_cs(code);
+		load_ss(data);
+		load_ds(data);
+		load_es(data);
+		load_tss(task);
+	}
+}
+
+impl EarlyConsole for X86_64 {
+	/// early_write() polls COM1 directly. the firmware or the serial driver
+	/// will have set it up by the time anyone's looking at it.
+	fn early_write(bytes: &[u8]) {
+		let mut data = Port::<u8>::new(COM1);
+		let mut line_status = Port::<u8>::new(COM1_LINE_STATUS);
+		for &byte in bytes {
+			unsafe {
+				while line_status.read() & LINE_STATUS_THR_EMPTY == 0 {}
+				data.write(byte);
+			}
+		}
+	}
+}
+
+impl ContextFrame for InterruptContext {
+	/// instruction_pointer() returns the saved rip
+	fn instruction_pointer(&self) -> u64 {
+		self.stack_frame.instruction_pointer
+	}
+
+	/// set_instruction_pointer() changes the saved rip
+	fn set_instruction_pointer(&mut self, address: u64) {
+		self.stack_frame.instruction_pointer = address;
+	}
+
+	/// stack_pointer() returns the saved rsp
+	fn stack_pointer(&self) -> u64 {
+		self.stack_frame.stack_pointer
+	}
+
+	/// interrupts_were_enabled() checks the saved IF
+	fn interrupts_were_enabled(&self) -> bool {
+		self.stack_frame.cpu_flags & RFlags::INTERRUPT_FLAG.bits() != 0
+	}
+
+	/// error_code() returns the saved error code
+	fn error_code(&self) -> u64 {
+		self.error_code
+	}
+
+	/// set_return_value() sets the saved rax
+	fn set_return_value(&mut self, value: u64) {
+		self.registers.scratch.rax = value;
+	}
+
+	/// syscall_number() returns the saved rax
+	fn syscall_number(&self) -> u64 {
+		self.registers.scratch.rax
+	}
+
+	/// syscall_args() returns the saved rdi, rsi, rdx, r10, r8 and r9
+	fn syscall_args(&self) -> [u64; 6] {
+		let scratch = &self.registers.scratch;
+		[scratch.rdi, scratch.rsi, scratch.rdx, scratch.r10, scratch.r8,
+			scratch.r9]
+	}
+
+	/// fmt_registers() writes out every general purpose register and the
+	/// stack frame the cpu pushed
+	fn fmt_registers(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let regs = &self.registers;
+		let scratch = &regs.scratch;
+		let frame = &self.stack_frame;
+
+		writeln!(f, "rax: {:#018x} rbx: {:#018x} rcx: {:#018x}",
+					scratch.rax, regs.rbx, scratch.rcx)?;
+		writeln!(f, "rdx: {:#018x} rsi: {:#018x} rdi: {:#018x}",
+					scratch.rdx, scratch.rsi, scratch.rdi)?;
+		writeln!(f, "rbp: {:#018x} rsp: {:#018x} r8:  {:#018x}",
+					regs.rbp, frame.stack_pointer, scratch.r8)?;
+		writeln!(f, "r9:  {:#018x} r10: {:#018x} r11: {:#018x}",
+					scratch.r9, scratch.r10, scratch.r11)?;
+		writeln!(f, "r12: {:#018x} r13: {:#018x} r14: {:#018x}",
+					regs.r12, regs.r13, regs.r14)?;
+		writeln!(f, "r15: {:#018x} rip: {:#018x}",
+					regs.r15, frame.instruction_pointer)?;
+		writeln!(f, "cs:  {:#06x} ss:  {:#06x}",
+					frame.code_segment, frame.stack_segment)?;
+		write!(f, "rflags: {:#x} (iopl {}) {:?}", frame.cpu_flags,
+				(frame.cpu_flags >> 12) & 0b11,
+				RFlags::from_bits_truncate(frame.cpu_flags))
+	}
+}
+
+impl ContextFrame for SyscallFrame {
+	/// instruction_pointer() returns the rip syscall saved in rcx
+	fn instruction_pointer(&self) -> u64 {
+		self.rip
+	}
+
+	/// set_instruction_pointer() changes the rip sysret goes back to. it has
+	/// to stay canonical, or sysret faults in ring 0.
+	fn set_instruction_pointer(&mut self, address: u64) {
+		self.rip = address;
+	}
+
+	/// stack_pointer() returns the user's rsp
+	fn stack_pointer(&self) -> u64 {
+		self.rsp
+	}
+
+	/// interrupts_were_enabled() checks the IF syscall saved in r11
+	fn interrupts_were_enabled(&self) -> bool {
+		self.rflags & RFlags::INTERRUPT_FLAG.bits() != 0
+	}
+
+	/// error_code() is always 0, since syscall isn't an exception
+	fn error_code(&self) -> u64 {
+		0
+	}
+
+	/// set_return_value() sets the saved rax
+	fn set_return_value(&mut self, value: u64) {
+		self.rax = value;
+	}
+
+	/// syscall_number() returns the saved rax
+	fn syscall_number(&self) -> u64 {
+		self.rax
+	}
+
+	/// syscall_args() returns the saved rdi, rsi, rdx, r10, r8 and r9
+	fn syscall_args(&self) -> [u64; 6] {
+		[self.rdi, self.rsi, self.rdx, self.r10, self.r8, self.r9]
+	}
+
+	/// fmt_registers() writes out what syscall_entry() saved. that's all
+	/// there is, since the rest belong to the kernel by now.
+	fn fmt_registers(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "rax: {:#018x} rdi: {:#018x} rsi: {:#018x}",
+					self.rax, self.rdi, self.rsi)?;
+		writeln!(f, "rdx: {:#018x} r10: {:#018x} r8:  {:#018x}",
+					self.rdx, self.r10, self.r8)?;
+		writeln!(f, "r9:  {:#018x} rip: {:#018x} rsp: {:#018x}",
+					self.r9, self.rip, self.rsp)?;
+		write!(f, "rflags: {:#x} {:?}", self.rflags,
+				RFlags::from_bits_truncate(self.rflags))
+	}
+}
@@ -0,0 +1,193 @@
+// file:	bench-heap-allocators.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Benchmark that runs the same allocation pattern against every heap
+//			allocator design, and compares their speed and fragmentation
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+#![feature(alloc)]
+
+// the benchmark's region comes out of the kernel heap
+extern crate alloc;
+
+// includes
+use core::alloc::Layout;
+use core::arch::x86_64::_rdtsc;
+use core::panic::PanicInfo;
+use core::ptr;
+use alloc::alloc::{alloc, dealloc};
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{dual_println, exit_qemu, serial_println};
+use posos::allocator::{self, BuddyAllocator, BumpAllocator,
+						FixedBlockAllocator, Heap, HeapAllocator, HeapStats,
+						LinkedListAllocator};
+use posos::memory;
+
+// how big each design's region is
+const REGION_SIZE: usize = 512 * 1024;
+// how many allocations can be live at once
+const SLOTS: usize = 256;
+// how many times a slot gets allocated or freed
+const ROUNDS: usize = 20_000;
+// the pattern's seed, so every design gets the same one
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("bench failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// BenchResult is how one design did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BenchResult {
+	// average TSC cycles per allocation or free
+	cycles_per_op: u64,
+	// the heap's stats at the end of the run, before it was emptied
+	stats: HeapStats,
+	// whether everything came back once it was all freed
+	reclaimed: bool,
+}
+
+// Slot is an allocation the pattern is holding on to
+#[derive(Clone, Copy)]
+struct Slot {
+	ptr: *mut u8,
+	size: usize,
+	align: usize,
+}
+
+/// next_random() steps a xorshift generator. it's not good randomness, but
+/// it's the same every run.
+fn next_random(state: &mut u64) -> u64 {
+	*state ^= *state << 13;
+	*state ^= *state >> 7;
+	*state ^= *state << 17;
+	*state
+}
+
+/// random_layout() picks the next allocation's size and alignment. it's
+/// mostly small things, some medium ones, and the odd page.
+fn random_layout(state: &mut u64) -> (usize, usize) {
+	let random = next_random(state);
+	let size = match random % 16 {
+		0 => 4096,
+		1..=3 => 256 + (random >> 8) as usize % 768,
+		_ => 8 + (random >> 8) as usize % 120,
+	};
+	let align = match (random >> 32) % 8 {
+		0 => 64,
+		1..=2 => 16,
+		_ => 8,
+	};
+	(size, align)
+}
+
+/// run() runs the pattern against one design in region, and returns how it
+/// did. region has to be REGION_SIZE bytes that nothing else is using.
+unsafe fn run<A: HeapAllocator>(allocator: A, region: *mut u8)
+	-> BenchResult
+{
+	let mut heap = Heap::new(allocator);
+	heap.init(region as usize, REGION_SIZE);
+	let empty = heap.stats().free;
+
+	let mut slots = [Slot { ptr: ptr::null_mut(), size: 0, align: 0 }; SLOTS];
+	let mut state = SEED;
+	let start = _rdtsc();
+	for _ in 0..ROUNDS {
+		let slot = &mut slots[next_random(&mut state) as usize % SLOTS];
+		if slot.ptr.is_null() {
+			let (size, align) = random_layout(&mut state);
+			let layout = Layout::from_size_align_unchecked(size, align);
+			slot.ptr = heap.allocate(layout);
+			slot.size = size;
+			slot.align = align;
+			// touch it, so a broken allocator shows up as a fault
+			if !slot.ptr.is_null() {
+				slot.ptr.write_volatile(0xa5);
+			}
+		} else {
+			let layout = Layout::from_size_align_unchecked(slot.size,
+															slot.align);
+			heap.deallocate(slot.ptr, layout);
+			slot.ptr = ptr::null_mut();
+		}
+	}
+	let cycles = _rdtsc() - start;
+	let stats = heap.stats();
+
+	// free whatever's left, which should leave the region as it started
+	for slot in slots.iter_mut().filter(|slot| !slot.ptr.is_null()) {
+		let layout = Layout::from_size_align_unchecked(slot.size, slot.align);
+		heap.deallocate(slot.ptr, layout);
+	}
+
+	let ops = stats.allocations + stats.deallocations + stats.failures;
+	BenchResult {
+		cycles_per_op: cycles / ops.max(1),
+		stats,
+		reclaimed: heap.stats().free == empty,
+	}
+}
+
+/// benchmark() runs the pattern against every design, prints a table of how
+/// they did, and returns the results in the order they're printed
+fn benchmark() -> [BenchResult; 4] {
+	let layout = Layout::from_size_align(REGION_SIZE, 4096).unwrap();
+	let region = unsafe { alloc(layout) };
+	assert!(!region.is_null(), "no room in the heap for the benchmark");
+
+	let results = unsafe {
+		[
+			run(BumpAllocator::new(), region),
+			run(LinkedListAllocator::new(), region),
+			run(FixedBlockAllocator::new(), region),
+			run(BuddyAllocator::new(), region),
+		]
+	};
+	unsafe { dealloc(region, layout) };
+
+	dual_println!("heap benchmark: {} rounds, {} slots, {} KiB each",
+					ROUNDS, SLOTS, REGION_SIZE / 1024);
+	dual_println!("allocator    cycles/op   allocs   failed  used KiB  \
+					frag%  reclaimed");
+	for result in results.iter() {
+		let stats = &result.stats;
+		dual_println!("{:<12} {:>9} {:>8} {:>8} {:>9} {:>6}  {}",
+						stats.name, result.cycles_per_op, stats.allocations,
+						stats.failures, stats.used / 1024,
+						stats.fragmentation(), result.reclaimed);
+	}
+	results
+}
+
+entry_point!(bench_main);
+
+/// bench_main() is where the bootloader drops us, with the memory map
+fn bench_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	let free = allocator::heap_free();
+
+	// once the pattern's allocations are all freed, every design should
+	// have its whole region back
+	for result in benchmark().iter() {
+		assert!(result.reclaimed, "{} leaked", result.stats.name);
+	}
+	assert_eq!(allocator::heap_free(), free);
+	serial_println!("kernel heap: {}", allocator::heap_stats());
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
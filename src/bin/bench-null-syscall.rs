@@ -0,0 +1,133 @@
+// file:	bench-null-syscall.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Benchmark that measures the round trip cost of a null syscall from
+//			ring 3, in TSC cycles
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm and naked functions for the ring 3 side of the benchmark
+#![feature(asm)]
+#![feature(core_intrinsics)]
+#![feature(naked_functions)]
+
+// includes
+use core::arch::x86_64::_rdtsc;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicU64, Ordering};
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, memory, serial_println};
+use posos::memory::address_space::AddressSpace;
+use posos::memory::paging;
+use posos::syscall::{self, Errno, SyscallArgs};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+
+// how many null syscalls to time
+const ITERATIONS: u64 = 100_000;
+// number the benchmark's ring 3 code calls once it's done
+const SYS_BENCH_DONE: u64 = 42;
+
+// where the ring 3 side's code and stack get mapped in its address space
+const USER_CODE: u64 = 0x0000_1000_0000_0000;
+const USER_STACK: u64 = 0x0000_1000_0010_0000;
+// how much of the ring 3 side's code has to be mapped. the loop is short,
+// but it might straddle a page boundary.
+const CODE_BYTES: u64 = 64;
+
+// tsc reading from right before we dropped to ring 3
+static START: AtomicU64 = AtomicU64::new(0);
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("bench failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// user_loop() is the ring 3 side. it makes ITERATIONS null syscalls, then
+/// tells the kernel it's done. it only touches registers that syscall_entry()
+/// preserves, and never returns.
+#[naked]
+extern "C" fn user_loop() -> ! {
+	unsafe {
+		// labels made of only 0s and 1s read as binary in intel syntax,
+		// hence starting at 2
+		asm!("mov r12, $0
+			2:
+				mov eax, $1
+				syscall
+				dec r12
+				jnz 2b
+				mov eax, $2
+				syscall
+			3:
+				jmp 3b"
+				:: "i"(ITERATIONS), "i"(syscall::SYS_NULL),
+				"i"(SYS_BENCH_DONE)
+				:: "intel", "volatile");
+		::core::intrinsics::unreachable();
+	}
+}
+
+/// sys_bench_done() reports how long the whole loop took per syscall, then
+/// exits, since there's nothing in ring 3 to go back to
+fn sys_bench_done(_args: &SyscallArgs) -> Result<u64, Errno> {
+	let cycles = unsafe { _rdtsc() } - START.load(Ordering::SeqCst);
+	serial_println!("null syscall: {} cycles per round trip ({} calls)",
+					cycles / ITERATIONS, ITERATIONS);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// map_code() maps the kernel pages code's first CODE_BYTES are on into
+/// space at USER_CODE, read only, and returns where code ends up
+fn map_code(space: &mut AddressSpace, code: u64) -> u64 {
+	let first: Page = Page::containing_address(VirtAddr::new(code));
+	let last: Page = Page::containing_address(VirtAddr::new(code + CODE_BYTES));
+	let user: Page = Page::containing_address(VirtAddr::new(USER_CODE));
+	for (i, page) in Page::range_inclusive(first, last).enumerate() {
+		let frame = paging::translate_page(page).expect("code isn't mapped");
+		unsafe {
+			space.map_user_to(user + i as u64, frame, PageTableFlags::empty())
+				.unwrap();
+		}
+	}
+	USER_CODE + (code - first.start_address().as_u64())
+}
+
+entry_point!(bench_main);
+
+/// bench_main() is where the bootloader drops us, with the memory map
+fn bench_main(boot_info: &'static BootInfo) -> ! {
+	// set up memory for the address space, then the idt, which sets up
+	// syscalls too
+	memory::init(boot_info);
+	posos::interrupts::init();
+	syscall::register_syscall(SYS_BENCH_DONE, sys_bench_done).unwrap();
+
+	// give ring 3 an address space with just the loop's code and a stack
+	let mut space = AddressSpace::new().unwrap();
+	let entry = map_code(&mut space, user_loop as u64);
+	let stack = Page::containing_address(VirtAddr::new(USER_STACK));
+	let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+	space.map_user(stack, flags).unwrap();
+
+	unsafe { space.switch_to() };
+	START.store(unsafe { _rdtsc() }, Ordering::SeqCst);
+	unsafe {
+		syscall::jump_to_user(VirtAddr::new(entry),
+								VirtAddr::new(USER_STACK + 4096));
+	}
+}
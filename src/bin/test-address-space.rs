@@ -0,0 +1,113 @@
+// file:	test-address-space.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests address spaces keep their user
+//			mappings apart, share the kernel's, and give back everything they
+//			allocated when they're dropped
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use core::ptr::{read_volatile, write_volatile};
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::memory::{self, paging};
+use posos::memory::address_space::{self, AddressSpace, SpaceError};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+
+// somewhere in the lower half the kernel doesn't use, under p4 entry 32
+const USER_ADDR: u64 = 0x0000_1000_0000_0000;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	posos::interrupts::init();
+	serial_println!("pcids: {}", address_space::pcid_enabled());
+
+	// make and drop one first, so the scratch page's tables are already
+	// there when we count frames
+	drop(AddressSpace::new().unwrap());
+	let frames = memory::frame_stats().free;
+
+	let page = Page::containing_address(VirtAddr::new(USER_ADDR));
+	let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+	let mut a = AddressSpace::new().unwrap();
+	let mut b = AddressSpace::new().unwrap();
+	assert_ne!(a.p4_frame(), b.p4_frame());
+	if address_space::pcid_enabled() {
+		assert!(a.pcid() != 0 && a.pcid() != b.pcid());
+	}
+
+	// the same page goes to a different frame in each, and nowhere in ours
+	let frame_a = a.map_user(page, flags).unwrap();
+	let frame_b = b.map_user(page, flags).unwrap();
+	assert_ne!(frame_a, frame_b);
+	assert_eq!(a.translate(VirtAddr::new(USER_ADDR)),
+				Some(frame_a.start_address()));
+	assert_eq!(b.translate(VirtAddr::new(USER_ADDR)),
+				Some(frame_b.start_address()));
+	assert_eq!(paging::translate_addr(VirtAddr::new(USER_ADDR)), None);
+	assert_eq!(a.map_user(page, flags),
+				Err(SpaceError::Paging(paging::PagingError::AlreadyMapped)));
+
+	// the kernel's mappings look the same from everywhere
+	let kernel = VirtAddr::new(test_main as u64);
+	assert_eq!(a.translate(kernel), paging::translate_addr(kernel));
+	let heap = VirtAddr::new(posos::allocator::HEAP_START);
+	assert_eq!(b.translate(heap), paging::translate_addr(heap));
+	let kernel_page = Page::containing_address(heap);
+	assert_eq!(a.map_user(kernel_page, flags), Err(SpaceError::KernelRange));
+
+	// writes through one space don't show up in the other
+	let target = USER_ADDR as *mut u64;
+	unsafe {
+		a.switch_to();
+		assert!(a.is_active());
+		write_volatile(target, 0xaaaa);
+		b.switch_to();
+		write_volatile(target, 0xbbbb);
+		a.switch_to();
+		assert_eq!(read_volatile(target), 0xaaaa);
+		b.switch_to();
+		assert_eq!(read_volatile(target), 0xbbbb);
+		address_space::switch_to_kernel();
+	}
+	assert!(!a.is_active() && !b.is_active());
+
+	// unmapping gives the frame back, and dropping gives back the rest
+	a.unmap_user(page).unwrap();
+	assert_eq!(a.translate(VirtAddr::new(USER_ADDR)), None);
+	assert_eq!(a.unmap_user(page),
+				Err(SpaceError::Paging(paging::PagingError::NotMapped)));
+	unsafe { b.switch_to() };
+	drop(a);
+	drop(b);
+	assert_eq!(paging::translate_addr(VirtAddr::new(USER_ADDR)), None);
+	assert_eq!(memory::frame_stats().free, frames);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,61 @@
+// file:	test-cpuid.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests CPUID feature detection against what
+//			any cpu we can be running on has to have
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+use posos::cpu::{self, FeatureFlags};
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	let features = cpu::features();
+
+	// we're in long mode, so all of this is a given
+	assert!(cpu::has_feature(FeatureFlags::LONG_MODE | FeatureFlags::FPU
+								| FeatureFlags::SSE | FeatureFlags::SSE2
+								| FeatureFlags::SYSCALL | FeatureFlags::PAE));
+	assert!(features.max_leaf >= 1);
+	assert!(features.max_extended_leaf >= 0x8000_0001);
+	assert_eq!(features.vendor_id().len(), 12);
+	assert!(features.physical_address_bits >= 32);
+	assert!(features.linear_address_bits >= 48);
+	assert!(features.topology.logical_per_package >= 1);
+
+	// every cache has to make sense on its own terms
+	for cache in features.caches() {
+		assert!(cache.level >= 1);
+		assert!(cache.line_size.is_power_of_two());
+		assert!(cache.size >= cache.line_size as usize);
+	}
+
+	// and it has to be the same answer every time
+	assert!(core::ptr::eq(features, cpu::features()));
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,63 @@
+// file:	test-exception-doublefault.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests double faults caused by kernel stack
+//			overflow, which only work if the handler gets its own IST stack
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+// fatal_hook() gets called once the double fault report has hit serial
+fn fatal_hook(vector: u8) {
+	if vector == 8 {
+		serial_println!("ok");
+	} else {
+		serial_println!("test failed: got vector {} instead of 8", vector);
+	}
+
+	unsafe { exit_qemu(); }
+}
+
+// stack_overflow() recurses until the guard page below the kernel stack gets
+// hit. the volatile read keeps the compiler from turning it into a loop.
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+	stack_overflow();
+	volatile::Volatile::new(0).read();
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	// initialize the gdt and idt, then hand the double fault to our hook
+	posos::interrupts::init();
+	posos::interrupts::set_fatal_hook(fatal_hook);
+
+	// blow the stack. without an IST stack this would triple fault
+	stack_overflow();
+
+	serial_println!("test failed: stack overflow returned");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,99 @@
+// file:	test-fpu-lazy.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests lazy FPU switching keeps each context's
+//			vector registers separate
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// allow asm for the purpose of touching xmm0
+#![feature(asm)]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+use posos::fpu::{self, FpuState};
+
+// the two pretend contexts' save areas
+static mut FIRST: FpuState = FpuState::new();
+static mut SECOND: FpuState = FpuState::new();
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// write_xmm0() puts a value in the low half of xmm0. the kernel's built
+/// without sse, so nothing but these two ever goes near it.
+fn write_xmm0(value: u64) {
+	unsafe {
+		asm!("movq xmm0, $0" :: "r"(value) :: "intel", "volatile");
+	}
+}
+
+/// read_xmm0() reads the low half of xmm0 back
+fn read_xmm0() -> u64 {
+	let value: u64;
+	unsafe {
+		asm!("movq $0, xmm0" : "=r"(value) ::: "intel", "volatile");
+	}
+	value
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	// initialize the idt, which turns on the fpu in lazy mode
+	posos::interrupts::init();
+
+	unsafe {
+		let first = &mut FIRST as *mut FpuState;
+		let second = &mut SECOND as *mut FpuState;
+
+		// each switch should cost exactly one #NM, on first use
+		fpu::switch_to(first);
+		write_xmm0(0x1111);
+		assert_eq!(fpu::lazy_restore_count(), 1);
+
+		fpu::switch_to(second);
+		assert_eq!(read_xmm0(), 0, "a fresh context saw someone else's xmm0");
+		write_xmm0(0x2222);
+		assert_eq!(fpu::lazy_restore_count(), 2);
+
+		fpu::switch_to(first);
+		assert_eq!(read_xmm0(), 0x1111);
+		fpu::switch_to(second);
+		assert_eq!(read_xmm0(), 0x2222);
+		assert_eq!(fpu::lazy_restore_count(), 4);
+
+		// switching back to whoever's already loaded shouldn't trap at all
+		fpu::switch_to(second);
+		assert_eq!(read_xmm0(), 0x2222);
+		assert_eq!(fpu::lazy_restore_count(), 4);
+	}
+
+	// the kernel borrowing the fpu can't leak into the running context
+	{
+		let _fpu = fpu::kernel_fpu();
+		assert_eq!(read_xmm0(), 0, "kernel_fpu() didn't start clean");
+		write_xmm0(0x3333);
+	}
+	assert_eq!(read_xmm0(), 0x2222);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,78 @@
+// file:	test-frame-allocator.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests the physical frame allocator only hands
+//			out usable frames, never twice, and takes them back
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use bootloader::bootinfo::{BootInfo, MemoryRegionType};
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::memory::{self, FRAME_SIZE};
+use x86_64::structures::paging::PhysFrame;
+
+// how many frames to grab at once
+const BATCH: usize = 64;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	let before = memory::frame_stats();
+	assert!(before.usable > 0 && before.usable <= before.total);
+	assert!(before.free < before.usable, "the bitmap wasn't reserved");
+
+	// every frame has to be in a usable region, and different
+	let mut frames = [None; BATCH];
+	for slot in frames.iter_mut() {
+		let frame = memory::allocate_frame().expect("out of frames");
+		let address = frame.start_address().as_u64();
+		assert!(address != 0, "handed out frame zero");
+		assert!(boot_info.memory_map.iter().any(|region|
+			region.region_type == MemoryRegionType::Usable
+				&& region.range.start_addr() <= address
+				&& address < region.range.end_addr()),
+			"frame {:#x} isn't in a usable region", address);
+		*slot = Some(frame);
+	}
+	for (i, a) in frames.iter().enumerate() {
+		for b in frames[i + 1..].iter() {
+			assert_ne!(a, b, "handed out the same frame twice");
+		}
+	}
+	assert_eq!(memory::frame_stats().free,
+				before.free - BATCH as u64 * FRAME_SIZE);
+
+	// they should all come back, and be the first ones handed out again
+	let first: PhysFrame = frames[0].unwrap();
+	for frame in frames.iter().filter_map(|frame| *frame) {
+		unsafe { memory::free_frame(frame) };
+	}
+	assert_eq!(memory::frame_stats().free, before.free);
+	assert_eq!(memory::allocate_frame(), Some(first));
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
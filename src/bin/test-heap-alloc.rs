@@ -0,0 +1,125 @@
+// file:	test-heap-alloc.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests the kernel heap with big allocations,
+//			lots of small ones, and reusing memory after it's freed
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+#![feature(alloc)]
+
+// Box, Vec and friends, backed by the kernel heap
+extern crate alloc;
+
+// includes
+use core::panic::PanicInfo;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::allocator::{self, HEAP_SIZE, HEAP_START};
+use posos::memory;
+
+// how many elements go in the big vec. that's 800KiB of u64s.
+const BIG: usize = 100_000;
+// how many small boxes to make
+const SMALL: usize = 10_000;
+// how many times to allocate and free the same sized block
+#[cfg(not(feature = "heap-bump"))]
+const ROUNDS: usize = 1000;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+entry_point!(test_main);
+
+/// on_heap() returns whether ptr points into the kernel heap
+fn on_heap<T>(ptr: *const T) -> bool {
+	let addr = ptr as u64;
+	addr >= HEAP_START && addr < HEAP_START + HEAP_SIZE
+}
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	let free = allocator::heap_free();
+	assert_eq!(free, HEAP_SIZE as usize);
+
+	// the usual suspects should all work
+	let boxed = Box::new(41u64);
+	assert!(on_heap(&*boxed as *const u64));
+	assert_eq!(*boxed + 1, 42);
+	let mut string = String::from("hello");
+	string.push_str(", heap");
+	assert_eq!(string, "hello, heap");
+	let mut map = BTreeMap::new();
+	for i in 0..100u32 {
+		map.insert(i, i * i);
+	}
+	assert_eq!(map.get(&9), Some(&81));
+	let shared = Arc::new(7u8);
+	let other = shared.clone();
+	assert_eq!(Arc::strong_count(&shared), 2);
+	drop(other);
+	assert_eq!(Arc::strong_count(&shared), 1);
+	drop((boxed, string, map, shared));
+
+	// something long-lived, to make sure nothing below scribbles on it
+	let keep = Box::new([0x5au8; 256]);
+
+	// one big allocation, grown a bit at a time
+	let mut big: Vec<u64> = Vec::new();
+	for i in 0..BIG as u64 {
+		big.push(i);
+	}
+	assert!(on_heap(big.as_ptr()));
+	assert_eq!(big.iter().sum::<u64>(), (BIG as u64 - 1) * BIG as u64 / 2);
+	drop(big);
+
+	// lots of little ones, which all have to be different
+	let mut small: Vec<Box<usize>> = Vec::with_capacity(SMALL);
+	for i in 0..SMALL {
+		small.push(Box::new(i));
+	}
+	for (i, boxed) in small.iter().enumerate() {
+		assert_eq!(**boxed, i);
+	}
+	drop(small);
+
+	// freed memory has to be reused, or this runs out of heap. 1000 rounds
+	// of 64KiB is way more than the heap holds. the bump allocator only
+	// reuses anything once every allocation is freed, and keep is still
+	// live, so it sits this one out.
+	#[cfg(not(feature = "heap-bump"))]
+	for round in 0..ROUNDS {
+		let block = alloc::vec![round as u8; 64 * 1024];
+		assert_eq!(block[block.len() - 1], round as u8);
+	}
+
+	assert!(keep.iter().all(|&byte| byte == 0x5a));
+	drop(keep);
+
+	// and everything freed should have merged back together
+	assert_eq!(allocator::heap_free(), free);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
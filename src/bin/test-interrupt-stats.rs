@@ -0,0 +1,82 @@
+// file:	test-interrupt-stats.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests the per-vector interrupt counters
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm for the purpose of making system calls
+#![feature(asm)]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+use posos::interrupts::{self, InterruptContext, IrqReturn};
+use posos::syscall;
+
+// how many times to hit each vector
+const ROUNDS: u64 = 3;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// tick() is a timer handler that doesn't need to do anything
+fn tick(_context: &mut InterruptContext) -> IrqReturn {
+	IrqReturn::Handled
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	// initialize the idt
+	posos::interrupts::init();
+
+	let breakpoints = interrupts::vector_stats(3);
+	let syscalls = interrupts::vector_stats(syscall::SYSCALL_VECTOR);
+	for _ in 0..ROUNDS {
+		x86_64::instructions::int3();
+		unsafe {
+			asm!("int 0x80" :: "{rax}"(syscall::SYS_NULL)
+				: "rax", "memory" : "intel", "volatile");
+		}
+	}
+
+	// every trip through the IDT should have been counted, and stamped
+	let after = interrupts::vector_stats(3);
+	assert_eq!(after.count - breakpoints.count, ROUNDS);
+	assert_eq!(after.spurious, 0);
+	assert_eq!(after.unhandled, 0);
+	assert!(after.last_tsc > breakpoints.last_tsc);
+	let after = interrupts::vector_stats(syscall::SYSCALL_VECTOR);
+	assert_eq!(after.count - syscalls.count, ROUNDS);
+	assert!(after.last_tsc > syscalls.last_tsc);
+
+	// and the timer should be counting by itself
+	interrupts::start_timer(100, tick).unwrap();
+	interrupts::wait_ms(50);
+	let timer = match interrupts::controller() {
+		interrupts::Controller::Apic => interrupts::TIMER_VECTOR,
+		interrupts::Controller::Pic => interrupts::IRQ_BASE_VECTOR,
+	};
+	assert!(interrupts::vector_stats(timer).count > 0);
+
+	interrupts::dump_interrupts();
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
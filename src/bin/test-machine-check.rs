@@ -0,0 +1,140 @@
+// file:	test-machine-check.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests machine checks get turned on, that
+//			polling picks up corrected errors and leaves uncorrected ones, and
+//			that #MC resumes or goes down depending on what the cpu says
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm, for reading cr4 and raising #MC
+#![feature(asm)]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+use posos::cpu::{self, FeatureFlags};
+use posos::interrupts;
+use x86_64::registers::model_specific::Msr;
+
+// the MSRs the test pokes at directly
+const IA32_MCG_CAP: u32 = 0x179;
+const IA32_MCG_STATUS: u32 = 0x17a;
+const IA32_MC0_CTL: u32 = 0x400;
+
+// MCG_STATUS bits
+const MCG_RIPV: u64 = 1 << 0;
+const MCG_MCIP: u64 = 1 << 2;
+
+// MCi_STATUS bits, and an error code to fake (internal parity)
+const STATUS_VAL: u64 = 1 << 63;
+const STATUS_UC: u64 = 1 << 61;
+const STATUS_ADDRV: u64 = 1 << 58;
+const STATUS_PCC: u64 = 1 << 57;
+const INTERNAL_PARITY: u64 = 0x0005;
+
+// the CR4 bit that lets #MC be delivered
+const CR4_MCE: u64 = 1 << 6;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+// fatal_hook() gets called once the unrecoverable #MC has been reported
+fn fatal_hook(vector: u8) {
+	if vector == 18 {
+		serial_println!("ok");
+	} else {
+		serial_println!("test failed: got vector {} instead of 18", vector);
+	}
+
+	unsafe { exit_qemu(); }
+}
+
+/// raise_machine_check() fakes an uncorrected error with the given status in
+/// a bank, then raises #MC the way the cpu would have
+fn raise_machine_check(bank: usize, status: u64, mcg_status: u64) {
+	unsafe {
+		bank_msr(bank, 1).write(status);
+		Msr::new(IA32_MCG_STATUS).write(mcg_status);
+		asm!("int 18" :::: "intel", "volatile");
+	}
+}
+
+/// bank_msr() returns one of a bank's registers, the same way mce.rs does
+fn bank_msr(bank: usize, register: u32) -> Msr {
+	Msr::new(IA32_MC0_CTL + bank as u32 * 4 + register)
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	interrupts::init();
+
+	// every cpu qemu emulates has both
+	assert!(cpu::has_feature(FeatureFlags::MCE | FeatureFlags::MCA));
+	let cr4: u64;
+	unsafe { asm!("mov $0, cr4" : "=r"(cr4) ::: "intel", "volatile") };
+	assert!(cr4 & CR4_MCE != 0, "CR4.MCE is off");
+
+	// every bank should be on, and empty
+	let banks = interrupts::machine_check_banks();
+	let cap = unsafe { Msr::new(IA32_MCG_CAP).read() };
+	assert!(banks > 0);
+	assert_eq!(banks as u64, cap & 0xff);
+	for bank in 0..banks {
+		unsafe {
+			assert_eq!(bank_msr(bank, 0).read(), !0);
+			assert_eq!(bank_msr(bank, 1).read(), 0);
+		}
+	}
+	assert_eq!(interrupts::poll_machine_checks(), 0);
+	assert_eq!(interrupts::corrected_error_count(), 0);
+
+	// qemu lets us write whatever we like to a bank's status, so fake a
+	// corrected error in the last one. polling should log and clear it.
+	let bank = banks - 1;
+	unsafe {
+		bank_msr(bank, 2).write(0x1234_5000);
+		bank_msr(bank, 1).write(STATUS_VAL | STATUS_ADDRV | INTERNAL_PARITY);
+	}
+	assert_eq!(interrupts::poll_machine_checks(), 1);
+	assert_eq!(interrupts::corrected_error_count(), 1);
+	assert_eq!(unsafe { bank_msr(bank, 1).read() }, 0);
+
+	// an uncorrected one is the #MC handler's, so polling leaves it be
+	let uncorrected = STATUS_VAL | STATUS_UC | INTERNAL_PARITY;
+	unsafe { bank_msr(bank, 1).write(uncorrected) };
+	assert_eq!(interrupts::poll_machine_checks(), 0);
+	assert_eq!(interrupts::corrected_error_count(), 1);
+	assert_eq!(unsafe { bank_msr(bank, 1).read() }, uncorrected);
+	unsafe { bank_msr(bank, 1).write(0) };
+
+	// the cpu says it can carry on, and the error didn't corrupt anything,
+	// so the handler should clear the bank and MCIP, and come back
+	raise_machine_check(bank, uncorrected, MCG_RIPV | MCG_MCIP);
+	assert_eq!(unsafe { bank_msr(bank, 1).read() }, 0);
+	assert_eq!(unsafe { Msr::new(IA32_MCG_STATUS).read() }, MCG_RIPV);
+
+	// with PCC set, there's no carrying on, so it should end up in our hook
+	interrupts::set_fatal_hook(fatal_hook);
+	raise_machine_check(bank, uncorrected | STATUS_PCC, MCG_RIPV | MCG_MCIP);
+
+	serial_println!("test failed: #MC with pcc set came back");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
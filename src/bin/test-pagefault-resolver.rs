@@ -0,0 +1,81 @@
+// file:	test-pagefault-resolver.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests resuming after a resolved page fault
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm for the purpose of making a page fault we can fix up
+#![feature(asm)]
+
+// includes
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use posos::{exit_qemu, serial_println};
+use posos::arch::ContextFrame;
+use posos::memory::fault::{self, PageFault, Resolution};
+use x86_64::VirtAddr;
+
+// the unmapped page the test faults on
+const BAD_PAGE: u64 = 0xdead_0000;
+
+// what the load should come back with once the fault's been fixed up
+const VALUE: u64 = 0x5ca1ab1e;
+// how long the faulting instruction, mov rax, [rbx + 8], is
+const LOAD_LEN: u64 = 4;
+// how many times the resolver has run
+static RESOLVED: AtomicUsize = AtomicUsize::new(0);
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// resolver() fixes the fault by faking up the load's result and skipping
+/// over it
+fn resolver(page_fault: &PageFault, context: &mut dyn ContextFrame)
+	-> Resolution
+{
+	assert_eq!(page_fault.address.as_u64(), BAD_PAGE + 8);
+	context.set_return_value(VALUE);
+	let rip = context.instruction_pointer();
+	context.set_instruction_pointer(rip + LOAD_LEN);
+	RESOLVED.fetch_add(1, Ordering::SeqCst);
+	Resolution::Resolved
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	// initialize the idt
+	posos::interrupts::init();
+	fault::register_resolver(VirtAddr::new(BAD_PAGE),
+								VirtAddr::new(BAD_PAGE + 4096), resolver)
+		.unwrap();
+
+	// the load faults, and the resolver does it for us
+	let value: u64;
+	unsafe {
+		asm!("mov rax, [rbx + 8]"
+			: "={rax}"(value) : "{rbx}"(BAD_PAGE) :: "intel", "volatile");
+	}
+	assert_eq!(value, VALUE);
+	assert_eq!(RESOLVED.load(Ordering::SeqCst), 1);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
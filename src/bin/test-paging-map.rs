@@ -0,0 +1,85 @@
+// file:	test-paging-map.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests mapping a fresh page at an address that
+//			used to fault, reading it back, and unmapping it again
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use core::ptr::{read_volatile, write_volatile};
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::memory::{self, paging};
+use posos::memory::paging::PagingError;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+
+// the address test-exception-pagefault faults on
+const CAFEBABE: u64 = 0xcafebabe;
+// somewhere else unmapped, for a second view of the same frame
+const ALIAS: u64 = 0xdead_b000;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	posos::interrupts::init();
+
+	let address = VirtAddr::new(CAFEBABE);
+	let page = Page::containing_address(address);
+	let alias = Page::containing_address(VirtAddr::new(ALIAS));
+	assert_eq!(paging::translate_addr(address), None);
+	assert_eq!(paging::translate_page(alias), None);
+
+	unsafe {
+		// map it, and it should read back what we write
+		let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+		let frame = paging::map_new(page, flags).unwrap();
+		let target = CAFEBABE as *mut u32;
+		write_volatile(target, 0xdeadbeef);
+		assert_eq!(read_volatile(target), 0xdeadbeef);
+		assert_eq!(paging::translate_addr(address),
+					Some(frame.start_address() + (CAFEBABE & 0xfff)));
+		assert_eq!(paging::map_to(page, frame, flags),
+					Err(PagingError::AlreadyMapped));
+
+		// a second mapping of the frame sees the same memory
+		paging::map_to(alias, frame, flags).unwrap();
+		let aliased = (ALIAS + (CAFEBABE & 0xfff)) as *mut u32;
+		assert_eq!(read_volatile(aliased), 0xdeadbeef);
+		write_volatile(aliased, 0x600dcafe);
+		assert_eq!(read_volatile(target), 0x600dcafe);
+
+		// and unmapping hands the frame back, and leaves the address unmapped
+		assert_eq!(paging::unmap(alias), Ok(frame));
+		assert_eq!(paging::unmap(page), Ok(frame));
+		assert_eq!(paging::unmap(page), Err(PagingError::NotMapped));
+		assert!(!memory::is_mapped(CAFEBABE));
+		memory::free_frame(frame);
+	}
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,79 @@
+// file:	test-pic-controller.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests IRQs on the 8259 PICs, even on a machine
+//			that has an APIC, and that the MADT still gets parsed
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicU32, Ordering};
+use posos::{acpi, exit_qemu, serial_println};
+use posos::interrupts::{self, Controller, ControllerPreference,
+						InterruptContext, IrqError, IrqReturn};
+
+// rate the test runs the timer at, and how long it waits on it
+const TIMER_HZ: u32 = 100;
+const WAIT_MS: u32 = 100;
+
+// how many times the timer has fired
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// tick() counts timer interrupts
+fn tick(_context: &mut InterruptContext) -> IrqReturn {
+	TICKS.fetch_add(1, Ordering::SeqCst);
+	IrqReturn::Handled
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	interrupts::init_with(ControllerPreference::Pic);
+	assert_eq!(interrupts::controller(), Controller::Pic);
+
+	// qemu's machines all have a MADT, whichever controller we end up on
+	let madt = acpi::madt().expect("no MADT");
+	assert!(madt.cpu_count >= 1);
+	assert!(madt.io_apics().count() >= 1);
+
+	// the PIT on IRQ 0 should tick at about the rate we asked for
+	interrupts::start_timer(TIMER_HZ, tick).unwrap();
+	interrupts::wait_ms(WAIT_MS);
+	let ticks = TICKS.load(Ordering::SeqCst);
+	let expected = TIMER_HZ * WAIT_MS / 1000;
+	assert!(ticks >= expected / 2 && ticks <= expected * 2,
+			"{} ticks in {}ms at {}Hz", ticks, WAIT_MS, TIMER_HZ);
+	assert!(interrupts::vector_stats(interrupts::IRQ_BASE_VECTOR).count > 0);
+
+	// a second timer can't have IRQ 0, and mustn't retune the first one
+	assert_eq!(interrupts::start_timer(TIMER_HZ * 10, tick),
+				Err(IrqError::AlreadyExclusive(0)));
+	let before = TICKS.load(Ordering::SeqCst);
+	interrupts::wait_ms(WAIT_MS);
+	let ticks = TICKS.load(Ordering::SeqCst) - before;
+	assert!(ticks <= expected * 2, "the PIT got retuned to {} ticks in {}ms",
+			ticks, WAIT_MS);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
@@ -0,0 +1,110 @@
+// file:	test-slab-cache.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests slab caches construct their objects,
+//			move slabs between lists, and give empty slabs back to the frame
+//			allocator
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use core::ptr::NonNull;
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::allocator::slab::{self, SlabError};
+use posos::memory;
+
+// what the constructor writes into every object
+const MAGIC: u64 = 0x51ab_51ab_51ab_51ab;
+// how many objects to hold at once. it's more than one slab's worth.
+const OBJECTS: usize = 200;
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// construct() is the test cache's constructor
+fn construct(object: *mut u8) {
+	unsafe { (object as *mut u64).write(MAGIC) };
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	let cache = slab::create_cache("test-object", 48, 16, Some(construct))
+		.unwrap();
+
+	// make a slab and give it back once, so the page tables for the slab
+	// window are already there when we count frames
+	let object = cache.alloc().unwrap();
+	unsafe { cache.free(object) };
+	assert!(cache.shrink() > 0);
+	let frames = memory::frame_stats().free;
+
+	assert_eq!(slab::find_cache("test-object"), Some(cache));
+	assert_eq!(slab::create_cache("test-object", 8, 8, None),
+				Err(SlabError::NameTaken));
+	assert_eq!(slab::create_cache("too-big", 64 * 1024, 8, None),
+				Err(SlabError::ObjectTooBig));
+	assert_eq!(slab::create_cache("bad-align", 8, 24, None),
+				Err(SlabError::BadAlignment));
+
+	// every object should be constructed, aligned, and different
+	let mut objects = [None; OBJECTS];
+	for slot in objects.iter_mut() {
+		let object = cache.alloc().expect("out of slabs");
+		assert_eq!(object.as_ptr() as usize % 16, 0);
+		assert_eq!(unsafe { *(object.as_ptr() as *const u64) }, MAGIC);
+		*slot = Some(object);
+	}
+	for (i, a) in objects.iter().enumerate() {
+		assert!(!objects[i + 1..].contains(a), "handed out an object twice");
+	}
+	let stats = cache.stats();
+	assert_eq!(stats.object_size, 48);
+	assert_eq!(stats.active_objects, OBJECTS);
+	assert!(stats.total_objects >= OBJECTS);
+	assert!(stats.full_slabs >= 1);
+	assert_eq!(stats.empty_slabs, 0);
+	slab::dump_slabinfo();
+
+	// freeing them all leaves only empty slabs, which go back as frames.
+	// objects go back constructed, as the constructor left them.
+	for object in objects.iter().filter_map(|object| *object) {
+		unsafe { cache.free(object) };
+	}
+	let stats = cache.stats();
+	assert_eq!(stats.active_objects, 0);
+	assert_eq!(stats.partial_slabs + stats.full_slabs, 0);
+	assert!(stats.empty_slabs >= 1);
+	assert_eq!(slab::reclaim(), stats.empty_slabs * stats.slab_pages);
+	assert_eq!(cache.stats().total_objects, 0);
+	assert_eq!(memory::frame_stats().free, frames);
+
+	// and the cache still works after it's been emptied out
+	let object: NonNull<u8> = cache.alloc().unwrap();
+	assert_eq!(unsafe { *(object.as_ptr() as *const u64) }, MAGIC);
+	unsafe { cache.free(object) };
+	assert_eq!(cache.shrink(), cache.stats().slab_pages);
+
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
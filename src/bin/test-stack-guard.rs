@@ -0,0 +1,106 @@
+// file:	test-stack-guard.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests kernel stacks get guard pages, and that
+//			overflowing one is reported as overflowing that stack by name
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm, for reading cr2 and switching stacks
+#![feature(asm)]
+
+// includes
+use core::panic::PanicInfo;
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, serial_println};
+use posos::memory::{self, stack};
+use x86_64::VirtAddr;
+
+// what the test's stack is called
+const STACK_NAME: &str = "test-thread";
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// fatal_hook() gets called once the overflow report has hit serial. the
+/// page fault can't be delivered on the stack that ran out, so it should
+/// have turned into a double fault with cr2 in our stack's guard.
+fn fatal_hook(vector: u8) {
+	let cr2: u64;
+	unsafe { asm!("mov $0, cr2" : "=r"(cr2) ::: "intel", "volatile") };
+	let stack = stack::overflowed_stack(VirtAddr::new(cr2));
+
+	if vector == 8 && stack.map(|stack| stack.name) == Some(STACK_NAME) {
+		serial_println!("ok");
+	} else {
+		serial_println!("test failed: vector {}, cr2 {:#x} in {:?}", vector,
+						cr2, stack);
+	}
+
+	unsafe { exit_qemu(); }
+}
+
+// stack_overflow() recurses until the guard page below the stack gets hit.
+// the volatile read keeps the compiler from turning it into a loop.
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+	stack_overflow();
+	volatile::Volatile::new(0).read();
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	posos::interrupts::init();
+	posos::interrupts::set_fatal_hook(fatal_hook);
+
+	// the boot stack and the TSS's stacks should all be guarded by now
+	let boot = stack::overflowed_stack(VirtAddr::new(0x57ab_ffff_fff8));
+	assert_eq!(boot.map(|stack| stack.name), Some("boot"));
+
+	// an allocated stack is mapped all the way up, and not below
+	let test_stack = stack::allocate_stack(STACK_NAME, 4).unwrap();
+	let (bottom, top) = (test_stack.bottom(), test_stack.top());
+	assert_eq!(top - bottom, 4 * 4096);
+	assert!(memory::is_mapped(bottom.as_u64()));
+	assert!(memory::is_mapped(top.as_u64() - 8));
+	assert!(!memory::is_mapped(bottom.as_u64() - 8));
+	assert_eq!(stack::overflowed_stack(top - 8u64), None);
+	assert_eq!(stack::overflowed_stack(bottom - 8u64).unwrap().name,
+				STACK_NAME);
+
+	// freeing it unmaps it, and takes its guard away
+	unsafe { stack::free_stack(test_stack) };
+	assert!(!memory::is_mapped(bottom.as_u64()));
+	assert_eq!(stack::overflowed_stack(bottom - 8u64), None);
+
+	// then run off the bottom of a fresh one
+	let test_stack = stack::allocate_stack(STACK_NAME, 4).unwrap();
+	unsafe {
+		asm!("mov rsp, $0
+			call $1"
+			:: "r"(test_stack.top().as_u64()), "r"(stack_overflow as usize)
+			: "memory" : "intel", "volatile");
+	}
+
+	serial_println!("test failed: stack overflow returned");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
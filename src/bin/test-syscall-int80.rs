@@ -0,0 +1,177 @@
+// file:	test-syscall-int80.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests the int 0x80 system call gate, from
+//			ring 0 and from ring 3
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// allow asm and naked functions for the purpose of making system calls,
+// and for the ring 3 side of the test
+#![feature(asm)]
+#![feature(core_intrinsics)]
+#![feature(naked_functions)]
+
+// includes
+use core::panic::PanicInfo;
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, memory, serial_println};
+use posos::memory::address_space::AddressSpace;
+use posos::memory::paging;
+use posos::syscall::{self, Errno, SyscallArgs};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+
+// number the test registers its own system call at
+const SYS_TEST: u64 = 42;
+// a number nothing is registered at
+const SYS_MISSING: u64 = 43;
+// number the ring 3 side reports its results at
+const SYS_USER_DONE: u64 = 44;
+
+// what the ring 3 side writes from its own memory
+const MESSAGE: &[u8] = b"int 0x80 from ring 3\n";
+
+// where the ring 3 side's code gets mapped in its address space, and the
+// page of memory it gets. the message goes at the bottom of that page, and
+// its stack grows down from the top.
+const USER_CODE: u64 = 0x0000_1000_0000_0000;
+const USER_DATA: u64 = 0x0000_1000_0010_0000;
+// how much of the ring 3 side's code has to be mapped. it's short, but it
+// might straddle a page boundary.
+const CODE_BYTES: u64 = 128;
+
+// kernel memory that ring 3 shouldn't be able to get the kernel to print.
+// it's page aligned so it can't share a page with anything ring 3 can see.
+#[repr(align(4096))]
+struct Secret([u8; 16]);
+static SECRET: Secret = Secret(*b"kernel secrets!\n");
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// sys_test() adds up all six arguments, so we know they all made it through
+fn sys_test(args: &SyscallArgs) -> Result<u64, Errno> {
+	Ok(args.rdi + args.rsi + args.rdx + args.r10 + args.r8 + args.r9)
+}
+
+/// int80() makes a system call through the int 0x80 gate
+fn int80(number: u64, args: [u64; 6]) -> u64 {
+	let result: u64;
+	unsafe {
+		asm!("int 0x80"
+			: "={rax}"(result)
+			: "{rax}"(number), "{rdi}"(args[0]), "{rsi}"(args[1]),
+				"{rdx}"(args[2]), "{r10}"(args[3]), "{r8}"(args[4]),
+				"{r9}"(args[5])
+			: "memory" : "intel", "volatile");
+	}
+	result
+}
+
+/// user_side() runs in ring 3. it pops a kernel address, then a buffer of
+/// its own and its length, off its stack, tries SYS_DEBUG_WRITE on each,
+/// and hands both results to SYS_USER_DONE. it never returns.
+#[naked]
+extern "C" fn user_side() -> ! {
+	unsafe {
+		asm!("pop rdi
+				mov esi, 16
+				mov eax, $0
+				int 0x80
+				mov r12, rax
+				pop rdi
+				pop rsi
+				mov eax, $0
+				int 0x80
+				mov rsi, rax
+				mov rdi, r12
+				mov eax, $1
+				int 0x80
+			2:
+				jmp 2b"
+				:: "i"(syscall::SYS_DEBUG_WRITE), "i"(SYS_USER_DONE)
+				:: "intel", "volatile");
+		::core::intrinsics::unreachable();
+	}
+}
+
+/// sys_user_done() checks what ring 3 got back, then exits, since there's
+/// nothing in ring 3 to go back to
+fn sys_user_done(args: &SyscallArgs) -> Result<u64, Errno> {
+	assert_eq!(args.rdi, Errno::EFAULT.as_return(),
+				"ring 3 got the kernel to read kernel memory");
+	assert_eq!(args.rsi, MESSAGE.len() as u64);
+	serial_println!("ok");
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+/// map_code() maps the kernel pages code's first CODE_BYTES are on into
+/// space at USER_CODE, read only, and returns where code ends up
+fn map_code(space: &mut AddressSpace, code: u64) -> u64 {
+	let first: Page = Page::containing_address(VirtAddr::new(code));
+	let last: Page = Page::containing_address(VirtAddr::new(code + CODE_BYTES));
+	let user: Page = Page::containing_address(VirtAddr::new(USER_CODE));
+	for (i, page) in Page::range_inclusive(first, last).enumerate() {
+		let frame = paging::translate_page(page).expect("code isn't mapped");
+		unsafe {
+			space.map_user_to(user + i as u64, frame, PageTableFlags::empty())
+				.unwrap();
+		}
+	}
+	USER_CODE + (code - first.start_address().as_u64())
+}
+
+entry_point!(test_main);
+
+/// test_main() is where the bootloader drops us, with the memory map
+fn test_main(boot_info: &'static BootInfo) -> ! {
+	memory::init(boot_info);
+	posos::interrupts::init();
+
+	syscall::register_syscall(SYS_TEST, sys_test).unwrap();
+
+	assert_eq!(int80(syscall::SYS_NULL, [0; 6]), 0);
+	assert_eq!(int80(SYS_TEST, [1, 2, 3, 4, 5, 6]), 21);
+	assert_eq!(int80(SYS_MISSING, [0; 6]), Errno::ENOSYS.as_return());
+
+	// from ring 0 the kernel's own memory isn't fair game either
+	let secret = &SECRET as *const _ as u64;
+	assert_eq!(int80(syscall::SYS_DEBUG_WRITE, [secret, 16, 0, 0, 0, 0]),
+				Errno::EFAULT.as_return());
+
+	// then go through the gate from ring 3, in an address space of its own
+	// that only has its code and one page of data in the user half. the
+	// arguments go on its stack in the order it pops them.
+	syscall::register_syscall(SYS_USER_DONE, sys_user_done).unwrap();
+	let mut space = AddressSpace::new().unwrap();
+	let entry = map_code(&mut space, user_side as u64);
+	let data = Page::containing_address(VirtAddr::new(USER_DATA));
+	let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+	space.map_user(data, flags).unwrap();
+	let args = [secret, USER_DATA, MESSAGE.len() as u64];
+	let stack = USER_DATA + 4096 - args.len() as u64 * 8;
+	unsafe {
+		space.switch_to();
+		let page = &mut *(USER_DATA as *mut [u8; 4096]);
+		page[..MESSAGE.len()].copy_from_slice(MESSAGE);
+		*(stack as *mut [u64; 3]) = args;
+		syscall::jump_to_user(VirtAddr::new(entry), VirtAddr::new(stack));
+	}
+}
@@ -0,0 +1,63 @@
+// file:	test-watchdog-lockup.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Integration test that tests the watchdog catching a hard lockup
+
+// disables the rust stl. required to run on bare metal.
+// disabling the stl breaks main() so we need to macro that off as well.
+// also, allow unused imports during testing
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
+
+// includes
+use core::panic::PanicInfo;
+use posos::{exit_qemu, serial_println};
+use posos::interrupts::{self, Controller, IrqError};
+
+// this function is called when rust panics. tells you why and then exits.
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+	serial_println!("test failed.");
+	serial_println!("{}", info);
+
+	unsafe { exit_qemu(); }
+	loop {}
+}
+
+// fatal_hook() gets called once the watchdog's report has hit serial
+fn fatal_hook(vector: u8) {
+	if vector == 2 {
+		serial_println!("ok");
+	} else {
+		serial_println!("test failed: got vector {} instead of 2", vector);
+	}
+
+	unsafe { exit_qemu(); }
+}
+
+// make a bare metal-friendly _start function. no_mangle muzzles the compiler
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+	// initialize the idt, then hand the lockup report to our hook
+	interrupts::init();
+	interrupts::set_fatal_hook(fatal_hook);
+
+	// the PICs can't deliver IRQ 0 as an NMI, so all there is to check
+	// without an APIC is that the watchdog says so
+	if interrupts::controller() == Controller::Pic {
+		assert_eq!(interrupts::start_watchdog(200),
+					Err(IrqError::NmiUnsupported(0)));
+		serial_println!("ok");
+
+		unsafe { exit_qemu(); }
+		loop {}
+	}
+	interrupts::start_watchdog(200).unwrap();
+
+	// lock up for real: nothing but an NMI gets us out of this
+	x86_64::instructions::interrupts::disable();
+	loop {}
+}
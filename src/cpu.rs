@@ -0,0 +1,432 @@
+// file:	cpu.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	CPUID parsing. everything the cpu says about itself gets read once,
+//			and the rest of the kernel checks features against that.
+
+// includes
+use core::arch::x86_64::{CpuidResult, __cpuid, __cpuid_count};
+use core::fmt;
+use core::str;
+use lazy_static::lazy_static;
+use crate::println;
+
+/// most cache levels and kinds we keep track of
+pub const MAX_CACHES: usize = 8;
+
+// the extended leaves start here
+const EXTENDED_BASE: u32 = 0x8000_0000;
+
+bitflags! {
+	/// FeatureFlags is every feature bit the kernel cares about, gathered
+	/// up from all the leaves they're spread across
+	pub struct FeatureFlags: u64 {
+		// leaf 1, edx
+		const FPU = 1 << 0;
+		const PSE = 1 << 1;
+		const TSC = 1 << 2;
+		const MSR = 1 << 3;
+		const PAE = 1 << 4;
+		const MCE = 1 << 5;
+		const CX8 = 1 << 6;
+		const APIC = 1 << 7;
+		const SEP = 1 << 8;
+		const MTRR = 1 << 9;
+		const PGE = 1 << 10;
+		const MCA = 1 << 11;
+		const CMOV = 1 << 12;
+		const PAT = 1 << 13;
+		const CLFLUSH = 1 << 14;
+		const MMX = 1 << 15;
+		const FXSR = 1 << 16;
+		const SSE = 1 << 17;
+		const SSE2 = 1 << 18;
+		const HTT = 1 << 19;
+		// leaf 1, ecx
+		const SSE3 = 1 << 20;
+		const SSSE3 = 1 << 21;
+		const CX16 = 1 << 22;
+		const PCID = 1 << 23;
+		const SSE4_1 = 1 << 24;
+		const SSE4_2 = 1 << 25;
+		const X2APIC = 1 << 26;
+		const POPCNT = 1 << 27;
+		const TSC_DEADLINE = 1 << 28;
+		const AES = 1 << 29;
+		const XSAVE = 1 << 30;
+		const OSXSAVE = 1 << 31;
+		const AVX = 1 << 32;
+		const RDRAND = 1 << 33;
+		const HYPERVISOR = 1 << 34;
+		// leaf 7, ebx and ecx
+		const FSGSBASE = 1 << 35;
+		const AVX2 = 1 << 36;
+		const SMEP = 1 << 37;
+		const ERMS = 1 << 38;
+		const INVPCID = 1 << 39;
+		const AVX512F = 1 << 40;
+		const RDSEED = 1 << 41;
+		const SMAP = 1 << 42;
+		const UMIP = 1 << 43;
+		const PKU = 1 << 44;
+		// extended leaves
+		const SYSCALL = 1 << 45;
+		const NX = 1 << 46;
+		const PAGE_1GB = 1 << 47;
+		const RDTSCP = 1 << 48;
+		const LONG_MODE = 1 << 49;
+		const INVARIANT_TSC = 1 << 50;
+		const TOPOLOGY_EXT = 1 << 51;
+	}
+}
+
+// which register bit means which flag, for each register that has flags in
+// it. anything not in here just gets ignored.
+const LEAF_1_EDX: &[(u32, FeatureFlags)] = &[
+	(0, FeatureFlags::FPU), (3, FeatureFlags::PSE), (4, FeatureFlags::TSC),
+	(5, FeatureFlags::MSR), (6, FeatureFlags::PAE), (7, FeatureFlags::MCE),
+	(8, FeatureFlags::CX8), (9, FeatureFlags::APIC), (11, FeatureFlags::SEP),
+	(12, FeatureFlags::MTRR), (13, FeatureFlags::PGE),
+	(14, FeatureFlags::MCA), (15, FeatureFlags::CMOV),
+	(16, FeatureFlags::PAT), (19, FeatureFlags::CLFLUSH),
+	(23, FeatureFlags::MMX), (24, FeatureFlags::FXSR),
+	(25, FeatureFlags::SSE), (26, FeatureFlags::SSE2),
+	(28, FeatureFlags::HTT),
+];
+const LEAF_1_ECX: &[(u32, FeatureFlags)] = &[
+	(0, FeatureFlags::SSE3), (9, FeatureFlags::SSSE3),
+	(13, FeatureFlags::CX16), (17, FeatureFlags::PCID),
+	(19, FeatureFlags::SSE4_1), (20, FeatureFlags::SSE4_2),
+	(21, FeatureFlags::X2APIC), (23, FeatureFlags::POPCNT),
+	(24, FeatureFlags::TSC_DEADLINE), (25, FeatureFlags::AES),
+	(26, FeatureFlags::XSAVE), (27, FeatureFlags::OSXSAVE),
+	(28, FeatureFlags::AVX), (30, FeatureFlags::RDRAND),
+	(31, FeatureFlags::HYPERVISOR),
+];
+const LEAF_7_EBX: &[(u32, FeatureFlags)] = &[
+	(0, FeatureFlags::FSGSBASE), (5, FeatureFlags::AVX2),
+	(7, FeatureFlags::SMEP), (9, FeatureFlags::ERMS),
+	(10, FeatureFlags::INVPCID), (16, FeatureFlags::AVX512F),
+	(18, FeatureFlags::RDSEED), (20, FeatureFlags::SMAP),
+];
+const LEAF_7_ECX: &[(u32, FeatureFlags)] = &[
+	(2, FeatureFlags::UMIP), (3, FeatureFlags::PKU),
+];
+const EXT_1_EDX: &[(u32, FeatureFlags)] = &[
+	(11, FeatureFlags::SYSCALL), (20, FeatureFlags::NX),
+	(26, FeatureFlags::PAGE_1GB), (27, FeatureFlags::RDTSCP),
+	(29, FeatureFlags::LONG_MODE),
+];
+const EXT_1_ECX: &[(u32, FeatureFlags)] = &[
+	(22, FeatureFlags::TOPOLOGY_EXT),
+];
+const EXT_7_EDX: &[(u32, FeatureFlags)] = &[
+	(8, FeatureFlags::INVARIANT_TSC),
+];
+
+/// Vendor is who made the cpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vendor {
+	Intel,
+	Amd,
+	Other,
+}
+
+/// CacheKind is what a cache holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+	Data,
+	Instruction,
+	Unified,
+}
+
+/// CacheInfo describes one cache
+#[derive(Debug, Clone, Copy)]
+pub struct CacheInfo {
+	pub level: u8,
+	pub kind: CacheKind,
+	// total size in bytes
+	pub size: usize,
+	pub line_size: u32,
+	pub ways: u32,
+	pub sets: u32,
+	// how many logical processors share it
+	pub shared_by: u32,
+}
+
+impl fmt::Display for CacheInfo {
+	/// fmt() prints a cache on a single line, like "L1d 32K 8-way 64B lines"
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let kind = match self.kind {
+			CacheKind::Data => "d",
+			CacheKind::Instruction => "i",
+			CacheKind::Unified => "",
+		};
+		write!(f, "L{}{} {}K {}-way {}B lines, shared by {}", self.level,
+				kind, self.size / 1024, self.ways, self.line_size,
+				self.shared_by)
+	}
+}
+
+/// Topology is where this cpu sits in the package
+#[derive(Debug, Clone, Copy)]
+pub struct Topology {
+	// the x2APIC id if there is one, otherwise the initial APIC id
+	pub apic_id: u32,
+	pub threads_per_core: u32,
+	pub logical_per_package: u32,
+}
+
+/// CpuFeatures is everything CPUID told us
+#[derive(Debug, Clone, Copy)]
+pub struct CpuFeatures {
+	pub vendor: Vendor,
+	vendor_id: [u8; 12],
+	brand: [u8; 48],
+	pub family: u32,
+	pub model: u32,
+	pub stepping: u32,
+	// the highest basic and extended leaves there are
+	pub max_leaf: u32,
+	pub max_extended_leaf: u32,
+	pub flags: FeatureFlags,
+	pub topology: Topology,
+	caches: [Option<CacheInfo>; MAX_CACHES],
+	pub physical_address_bits: u8,
+	pub linear_address_bits: u8,
+}
+
+lazy_static! {
+	// CPUID only gets asked once. it's a serializing instruction, and slow
+	// under a hypervisor, so nobody should be calling it in a hot path.
+	static ref FEATURES: CpuFeatures = CpuFeatures::detect();
+}
+
+/// features() returns what the cpu supports
+pub fn features() -> &'static CpuFeatures {
+	&FEATURES
+}
+
+/// has_feature() returns whether the cpu has every feature in flags
+pub fn has_feature(flags: FeatureFlags) -> bool {
+	FEATURES.flags.contains(flags)
+}
+
+/// cpuid() is __cpuid_count, for leaves that take a subleaf
+fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+	unsafe { __cpuid_count(leaf, subleaf) }
+}
+
+/// collect() turns the bits of a register into flags, using one of the
+/// tables above
+fn collect(register: u32, table: &[(u32, FeatureFlags)]) -> FeatureFlags {
+	table.iter()
+		.filter(|(bit, _)| register & (1u32 << *bit) != 0)
+		.fold(FeatureFlags::empty(), |flags, (_, flag)| flags | *flag)
+}
+
+/// bits() pulls the bits from low to high, inclusive, out of a register
+fn bits(register: u32, low: u32, high: u32) -> u32 {
+	(register >> low) & (!0u32 >> (31 - (high - low)))
+}
+
+impl CpuFeatures {
+	/// detect() reads every leaf we know about. leaves past the maximums the
+	/// cpu reports return garbage, so those get left at their defaults.
+	fn detect() -> CpuFeatures {
+		let leaf_0 = unsafe { __cpuid(0) };
+		let max_leaf = leaf_0.eax;
+		let mut vendor_id = [0u8; 12];
+		vendor_id[0..4].copy_from_slice(&leaf_0.ebx.to_le_bytes());
+		vendor_id[4..8].copy_from_slice(&leaf_0.edx.to_le_bytes());
+		vendor_id[8..12].copy_from_slice(&leaf_0.ecx.to_le_bytes());
+		let vendor = match &vendor_id {
+			b"GenuineIntel" => Vendor::Intel,
+			b"AuthenticAMD" => Vendor::Amd,
+			_ => Vendor::Other,
+		};
+		let max_extended_leaf = unsafe { __cpuid(EXTENDED_BASE).eax };
+
+		let mut features = CpuFeatures {
+			vendor,
+			vendor_id,
+			brand: [0; 48],
+			family: 0,
+			model: 0,
+			stepping: 0,
+			max_leaf,
+			max_extended_leaf,
+			flags: FeatureFlags::empty(),
+			topology: Topology {
+				apic_id: 0,
+				threads_per_core: 1,
+				logical_per_package: 1,
+			},
+			caches: [None; MAX_CACHES],
+			physical_address_bits: 36,
+			linear_address_bits: 48,
+		};
+
+		features.read_basic_leaves();
+		features.read_extended_leaves();
+		features.read_topology();
+		features.read_caches();
+		features
+	}
+
+	/// read_basic_leaves() reads the signature and feature bits from leaves
+	/// 1 and 7
+	fn read_basic_leaves(&mut self) {
+		if self.max_leaf >= 1 {
+			let leaf_1 = cpuid(1, 0);
+			// the extended family and model only count for some families
+			let base_family = bits(leaf_1.eax, 8, 11);
+			let base_model = bits(leaf_1.eax, 4, 7);
+			self.family = if base_family == 0xf {
+				base_family + bits(leaf_1.eax, 20, 27)
+			} else {
+				base_family
+			};
+			self.model = if base_family == 0x6 || base_family == 0xf {
+				bits(leaf_1.eax, 16, 19) << 4 | base_model
+			} else {
+				base_model
+			};
+			self.stepping = bits(leaf_1.eax, 0, 3);
+			self.flags |= collect(leaf_1.edx, LEAF_1_EDX)
+				| collect(leaf_1.ecx, LEAF_1_ECX);
+		}
+		if self.max_leaf >= 7 {
+			let leaf_7 = cpuid(7, 0);
+			self.flags |= collect(leaf_7.ebx, LEAF_7_EBX)
+				| collect(leaf_7.ecx, LEAF_7_ECX);
+		}
+	}
+
+	/// read_extended_leaves() reads the extended feature bits, the brand
+	/// string and the address widths
+	fn read_extended_leaves(&mut self) {
+		let max = self.max_extended_leaf;
+		if max >= EXTENDED_BASE + 1 {
+			let ext_1 = cpuid(EXTENDED_BASE + 1, 0);
+			self.flags |= collect(ext_1.edx, EXT_1_EDX)
+				| collect(ext_1.ecx, EXT_1_ECX);
+		}
+		if max >= EXTENDED_BASE + 4 {
+			for i in 0..3 {
+				let leaf = cpuid(EXTENDED_BASE + 2 + i as u32, 0);
+				let registers = [leaf.eax, leaf.ebx, leaf.ecx, leaf.edx];
+				for (j, register) in registers.iter().enumerate() {
+					let at = i * 16 + j * 4;
+					self.brand[at..at + 4]
+						.copy_from_slice(&register.to_le_bytes());
+				}
+			}
+		}
+		if max >= EXTENDED_BASE + 7 {
+			self.flags |= collect(cpuid(EXTENDED_BASE + 7, 0).edx, EXT_7_EDX);
+		}
+		if max >= EXTENDED_BASE + 8 {
+			let ext_8 = cpuid(EXTENDED_BASE + 8, 0).eax;
+			self.physical_address_bits = bits(ext_8, 0, 7) as u8;
+			self.linear_address_bits = bits(ext_8, 8, 15) as u8;
+		} else if !self.flags.contains(FeatureFlags::PAE) {
+			self.physical_address_bits = 32;
+		}
+	}
+
+	/// read_topology() works out the APIC id and how many threads there are,
+	/// from leaf 0xb if the cpu has it and leaf 1 otherwise
+	fn read_topology(&mut self) {
+		if self.max_leaf >= 0xb && cpuid(0xb, 0).ebx != 0 {
+			// subleaf 0 is the SMT level, and subleaf 1 the core level.
+			// ebx is how many logical processors there are at each.
+			let smt = cpuid(0xb, 0);
+			let core = cpuid(0xb, 1);
+			self.topology = Topology {
+				apic_id: smt.edx,
+				threads_per_core: bits(smt.ebx, 0, 15).max(1),
+				logical_per_package: bits(core.ebx, 0, 15).max(1),
+			};
+		} else if self.max_leaf >= 1 {
+			let ebx = cpuid(1, 0).ebx;
+			self.topology.apic_id = bits(ebx, 24, 31);
+			if self.flags.contains(FeatureFlags::HTT) {
+				self.topology.logical_per_package = bits(ebx, 16, 23).max(1);
+			}
+		}
+	}
+
+	/// read_caches() walks the deterministic cache parameters. intel has
+	/// them in leaf 4, and amd in 0x8000_001d, in the same format.
+	fn read_caches(&mut self) {
+		let leaf = match self.vendor {
+			Vendor::Intel if self.max_leaf >= 4 => 4,
+			Vendor::Amd if self.flags.contains(FeatureFlags::TOPOLOGY_EXT)
+				&& self.max_extended_leaf >= EXTENDED_BASE + 0x1d =>
+				EXTENDED_BASE + 0x1d,
+			_ => return,
+		};
+
+		for (subleaf, slot) in self.caches.iter_mut().enumerate() {
+			let info = cpuid(leaf, subleaf as u32);
+			let kind = match bits(info.eax, 0, 4) {
+				1 => CacheKind::Data,
+				2 => CacheKind::Instruction,
+				3 => CacheKind::Unified,
+				// 0 means there are no more caches
+				_ => break,
+			};
+			let line_size = bits(info.ebx, 0, 11) + 1;
+			let partitions = bits(info.ebx, 12, 21) + 1;
+			let ways = bits(info.ebx, 22, 31) + 1;
+			let sets = info.ecx + 1;
+			*slot = Some(CacheInfo {
+				level: bits(info.eax, 5, 7) as u8,
+				kind,
+				size: (line_size * partitions * ways) as usize * sets as usize,
+				line_size,
+				ways,
+				sets,
+				shared_by: bits(info.eax, 14, 25) + 1,
+			});
+		}
+	}
+
+	/// vendor_id() returns the vendor string, like "GenuineIntel"
+	pub fn vendor_id(&self) -> &str {
+		str::from_utf8(&self.vendor_id).unwrap_or("unknown")
+	}
+
+	/// brand() returns the brand string, or "" if the cpu doesn't have one.
+	/// it's padded with spaces at the front and NULs at the back, which get
+	/// trimmed off.
+	pub fn brand(&self) -> &str {
+		let end = self.brand.iter().position(|&byte| byte == 0)
+			.unwrap_or(self.brand.len());
+		str::from_utf8(&self.brand[..end]).unwrap_or("").trim()
+	}
+
+	/// caches() iterates over every cache the cpu described
+	pub fn caches(&self) -> impl Iterator<Item = &CacheInfo> {
+		self.caches.iter().filter_map(Option::as_ref)
+	}
+}
+
+/// print_summary() prints what the cpu is and what it can do. _start calls
+/// this at boot, so a log says up front what hardware it came from.
+pub fn print_summary() {
+	let cpu = features();
+	println!("cpu: {} ({})", cpu.brand(), cpu.vendor_id());
+	println!("cpu: family {:#x} model {:#x} stepping {}, apic id {}, \
+			{} threads per core, {} per package",
+			cpu.family, cpu.model, cpu.stepping, cpu.topology.apic_id,
+			cpu.topology.threads_per_core,
+			cpu.topology.logical_per_package);
+	println!("cpu: {} bit physical, {} bit linear addresses",
+			cpu.physical_address_bits, cpu.linear_address_bits);
+	for cache in cpu.caches() {
+		println!("cpu: {}", cache);
+	}
+	println!("cpu: {:?}", cpu.flags);
+}
@@ -0,0 +1,354 @@
+// file:	fpu.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	x87/SSE/AVX state management. the kernel itself is built
+//			soft-float, so interrupts never touch the vector registers, and
+//			the only state that needs saving is whatever contexts are running
+//			on top of it, which gets switched eagerly or lazily off of #NM.
+
+// includes
+use core::arch::x86_64::__cpuid_count;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize,
+							Ordering};
+use crate::arch::{Arch, Interrupts};
+use crate::cpu::{self, FeatureFlags};
+
+/// biggest save area we have room for. everything up to AVX-512 fits.
+pub const STATE_SIZE: usize = 4096;
+
+// CR0 bits
+const CR0_MP: u64 = 1 << 1;
+const CR0_EM: u64 = 1 << 2;
+const CR0_TS: u64 = 1 << 3;
+const CR0_NE: u64 = 1 << 5;
+
+// CR4 bits
+const CR4_OSFXSR: u64 = 1 << 9;
+const CR4_OSXMMEXCPT: u64 = 1 << 10;
+const CR4_OSXSAVE: u64 = 1 << 18;
+
+// XCR0 state components
+const XCR0_X87: u64 = 1 << 0;
+const XCR0_SSE: u64 = 1 << 1;
+const XCR0_AVX: u64 = 1 << 2;
+const XCR0_AVX512: u64 = 0b111 << 5;
+
+// the x87 control word and MXCSR you get after a reset, with every
+// exception masked
+const DEFAULT_FCW: u16 = 0x037f;
+const DEFAULT_MXCSR: u32 = 0x1f80;
+
+/// SwitchMode is when a context's state gets loaded into the registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchMode {
+	// on every switch_to()
+	Eager,
+	// the first time the context actually uses the FPU after switch_to()
+	Lazy,
+}
+
+/// FpuState is a save area for one context's FPU and vector registers. the
+/// first 32 bytes are the legacy header, which are spelled out so a fresh
+/// one can start with everything masked.
+// the cpu's the one reading these, so rust thinks they're unused
+#[allow(dead_code)]
+#[repr(C, align(64))]
+pub struct FpuState {
+	fcw: u16,
+	fsw: u16,
+	ftw: u8,
+	reserved: u8,
+	fop: u16,
+	fip: u64,
+	fdp: u64,
+	mxcsr: u32,
+	mxcsr_mask: u32,
+	// the registers, and for XSAVE the header and extended components. an
+	// all-zero XSAVE header means everything past the legacy area is in
+	// its initial state.
+	rest: [u8; STATE_SIZE - 32],
+}
+
+impl FpuState {
+	/// new() is the constructor for FpuState. it holds the state a context
+	/// starts out in, with zeroed registers and every exception masked.
+	pub const fn new() -> FpuState {
+		FpuState {
+			fcw: DEFAULT_FCW,
+			fsw: 0,
+			ftw: 0,
+			reserved: 0,
+			fop: 0,
+			fip: 0,
+			fdp: 0,
+			mxcsr: DEFAULT_MXCSR,
+			mxcsr_mask: 0,
+			rest: [0; STATE_SIZE - 32],
+		}
+	}
+}
+
+// whether init() has run
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// whether we're switching lazily
+static LAZY: AtomicBool = AtomicBool::new(false);
+// the XCR0 components to save and restore, or 0 to use FXSAVE
+static XSAVE_MASK: AtomicU64 = AtomicU64::new(0);
+// how big the save area actually is, for whatever we enabled
+static SAVE_SIZE: AtomicUsize = AtomicUsize::new(512);
+// the state of the context that's running now
+static CURRENT: AtomicPtr<FpuState> = AtomicPtr::new(ptr::null_mut());
+// the state whose registers are actually loaded. with lazy switching, this
+// can lag behind CURRENT, and it's null while the kernel has them.
+static OWNER: AtomicPtr<FpuState> = AtomicPtr::new(ptr::null_mut());
+// whether the kernel's borrowed the FPU right now
+static KERNEL_OWNED: AtomicBool = AtomicBool::new(false);
+// how many times #NM has had to load someone's state
+static LAZY_RESTORES: AtomicUsize = AtomicUsize::new(0);
+
+// the state of whatever was running at boot
+static mut BOOT_STATE: FpuState = FpuState::new();
+// a state nobody runs in, for the kernel to start from when it borrows the
+// FPU
+static CLEAN_STATE: FpuState = FpuState::new();
+
+/// init() turns on the FPU, SSE and AVX, with lazy switching
+pub fn init() {
+	init_with(SwitchMode::Lazy);
+}
+
+/// init_with() turns on the FPU, SSE, and AVX and AVX-512 if the cpu has
+/// them. XSAVE gets used for saving state if it's there, and FXSAVE if not.
+/// whatever's running now becomes the first context.
+pub fn init_with(mode: SwitchMode) {
+	assert!(cpu::has_feature(FeatureFlags::FPU | FeatureFlags::FXSR
+								| FeatureFlags::SSE | FeatureFlags::SSE2),
+			"long mode without SSE2?");
+
+	unsafe {
+		// MP makes wait/fwait honor TS too, and NE reports x87 errors as #MF
+		// rather than through the ancient external pin
+		write_cr0((read_cr0() & !(CR0_EM | CR0_TS)) | CR0_MP | CR0_NE);
+		let mut cr4 = read_cr4() | CR4_OSFXSR | CR4_OSXMMEXCPT;
+		if cpu::has_feature(FeatureFlags::XSAVE) {
+			cr4 |= CR4_OSXSAVE;
+		}
+		write_cr4(cr4);
+
+		if cpu::has_feature(FeatureFlags::XSAVE) {
+			enable_xsave();
+		}
+		restore(&CLEAN_STATE);
+
+		let boot = &mut BOOT_STATE as *mut FpuState;
+		CURRENT.store(boot, Ordering::SeqCst);
+		OWNER.store(boot, Ordering::SeqCst);
+	}
+	LAZY.store(mode == SwitchMode::Lazy, Ordering::SeqCst);
+	ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// enable_xsave() picks which components to turn on in XCR0, out of the
+/// ones the cpu supports, and works out how big their save area is
+unsafe fn enable_xsave() {
+	let mut wanted = XCR0_X87 | XCR0_SSE;
+	if cpu::has_feature(FeatureFlags::AVX) {
+		wanted |= XCR0_AVX;
+	}
+	if cpu::has_feature(FeatureFlags::AVX512F) {
+		wanted |= XCR0_AVX512;
+	}
+
+	// leaf 0xd says which components the cpu can save, and ebx says how
+	// big the area is for what's turned on in XCR0 right now
+	let supported = __cpuid_count(0xd, 0).eax as u64;
+	let mut mask = wanted & supported;
+	xsetbv(0, mask);
+	if __cpuid_count(0xd, 0).ebx as usize > STATE_SIZE {
+		mask &= !XCR0_AVX512;
+		xsetbv(0, mask);
+	}
+	SAVE_SIZE.store(__cpuid_count(0xd, 0).ebx as usize, Ordering::SeqCst);
+	XSAVE_MASK.store(mask, Ordering::SeqCst);
+}
+
+/// save_area_size() returns how much of an FpuState actually gets used
+pub fn save_area_size() -> usize {
+	SAVE_SIZE.load(Ordering::SeqCst)
+}
+
+/// uses_xsave() returns whether state gets saved with XSAVE
+pub fn uses_xsave() -> bool {
+	XSAVE_MASK.load(Ordering::SeqCst) != 0
+}
+
+/// lazy_restore_count() returns how many times #NM has loaded a context's
+/// state
+pub fn lazy_restore_count() -> usize {
+	LAZY_RESTORES.load(Ordering::Relaxed)
+}
+
+/// switch_to() makes state the running context's. with eager switching the
+/// old owner's registers get saved and state's loaded right away. with lazy
+/// switching, TS gets set instead, and that happens on the next #NM, if
+/// there is one. state has to stay where it is until it's switched away
+/// from and some other context has used the FPU.
+pub unsafe fn switch_to(state: *mut FpuState) {
+	Arch::without_interrupts(|| {
+		CURRENT.store(state, Ordering::SeqCst);
+		let owner = OWNER.load(Ordering::SeqCst);
+		if owner == state {
+			clts();
+		} else if LAZY.load(Ordering::SeqCst) {
+			write_cr0(read_cr0() | CR0_TS);
+		} else {
+			load_current(owner);
+		}
+	});
+}
+
+/// forget() makes sure state won't get saved into again, for when a
+/// context is going away and its state with it
+pub unsafe fn forget(state: *mut FpuState) {
+	Arch::without_interrupts(|| {
+		if OWNER.load(Ordering::SeqCst) == state {
+			OWNER.store(ptr::null_mut(), Ordering::SeqCst);
+		}
+	});
+}
+
+/// load_current() saves the owner's registers, if there is one, and loads
+/// the running context's. interrupts have to be off.
+unsafe fn load_current(owner: *mut FpuState) {
+	let current = CURRENT.load(Ordering::SeqCst);
+	clts();
+	if !owner.is_null() {
+		save(owner);
+	}
+	restore(current);
+	OWNER.store(current, Ordering::SeqCst);
+}
+
+/// handle_device_not_available() is called from the IDT for #NM. if it's
+/// the lazy switch catching up, the running context's state gets loaded and
+/// the instruction gets run again. returns false if it wasn't that.
+pub(crate) fn handle_device_not_available() -> bool {
+	if !ENABLED.load(Ordering::SeqCst)
+		|| KERNEL_OWNED.load(Ordering::SeqCst)
+		|| CURRENT.load(Ordering::SeqCst).is_null()
+	{
+		return false;
+	}
+	unsafe { load_current(OWNER.load(Ordering::SeqCst)) };
+	LAZY_RESTORES.fetch_add(1, Ordering::Relaxed);
+	true
+}
+
+/// KernelFpu lets kernel code use the FPU until it's dropped. the kernel is
+/// built soft-float, so the code using it has to opt in itself, with
+/// #[target_feature] or asm. interrupts stay off the whole time, since
+/// nothing else in the kernel saves the registers, and it doesn't nest.
+pub struct KernelFpu {
+	// whether interrupts were enabled when the FPU got borrowed
+	were_enabled: bool,
+}
+
+/// kernel_fpu() saves the running context's registers, if they're loaded,
+/// and hands the FPU to the kernel in a clean state
+pub fn kernel_fpu() -> KernelFpu {
+	assert!(ENABLED.load(Ordering::SeqCst), "the FPU isn't enabled");
+	let were_enabled = Arch::interrupts_enabled();
+	Arch::disable_interrupts();
+	assert!(!KERNEL_OWNED.swap(true, Ordering::SeqCst),
+			"kernel FPU use doesn't nest");
+
+	unsafe {
+		clts();
+		let owner = OWNER.swap(ptr::null_mut(), Ordering::SeqCst);
+		if !owner.is_null() {
+			save(owner);
+		}
+		restore(&CLEAN_STATE);
+	}
+	KernelFpu { were_enabled }
+}
+
+impl Drop for KernelFpu {
+	/// drop() gives the FPU back to the running context. with lazy
+	/// switching, that's just setting TS, since there's no owner anymore.
+	fn drop(&mut self) {
+		unsafe {
+			if LAZY.load(Ordering::SeqCst) {
+				write_cr0(read_cr0() | CR0_TS);
+			} else {
+				load_current(ptr::null_mut());
+			}
+		}
+		KERNEL_OWNED.store(false, Ordering::SeqCst);
+		if self.were_enabled {
+			Arch::enable_interrupts();
+		}
+	}
+}
+
+/// save() saves the registers into state
+unsafe fn save(state: *mut FpuState) {
+	let mask = XSAVE_MASK.load(Ordering::SeqCst);
+	if mask != 0 {
+		asm!("xsave64 [$0]"
+			:: "r"(state), "{eax}"(mask as u32), "{edx}"((mask >> 32) as u32)
+			: "memory" : "intel", "volatile");
+	} else {
+		asm!("fxsave64 [$0]" :: "r"(state) : "memory" : "intel", "volatile");
+	}
+}
+
+/// restore() loads the registers from state
+unsafe fn restore(state: *const FpuState) {
+	let mask = XSAVE_MASK.load(Ordering::SeqCst);
+	if mask != 0 {
+		asm!("xrstor64 [$0]"
+			:: "r"(state), "{eax}"(mask as u32), "{edx}"((mask >> 32) as u32)
+			: "memory" : "intel", "volatile");
+	} else {
+		asm!("fxrstor64 [$0]" :: "r"(state) : "memory" : "intel", "volatile");
+	}
+}
+
+/// clts() clears TS, so the FPU can be used without trapping
+unsafe fn clts() {
+	asm!("clts" :::: "intel", "volatile");
+}
+
+/// xsetbv() writes an extended control register
+unsafe fn xsetbv(register: u32, value: u64) {
+	asm!("xsetbv"
+		:: "{ecx}"(register), "{eax}"(value as u32),
+		"{edx}"((value >> 32) as u32)
+		:: "intel", "volatile");
+}
+
+/// read_cr0() returns CR0
+unsafe fn read_cr0() -> u64 {
+	let cr0: u64;
+	asm!("mov $0, cr0" : "=r"(cr0) ::: "intel", "volatile");
+	cr0
+}
+
+/// write_cr0() sets CR0
+unsafe fn write_cr0(cr0: u64) {
+	asm!("mov cr0, $0" :: "r"(cr0) :: "intel", "volatile");
+}
+
+/// read_cr4() returns CR4
+unsafe fn read_cr4() -> u64 {
+	let cr4: u64;
+	asm!("mov $0, cr4" : "=r"(cr4) ::: "intel", "volatile");
+	cr4
+}
+
+/// write_cr4() sets CR4
+unsafe fn write_cr4(cr4: u64) {
+	asm!("mov cr4, $0" :: "r"(cr4) :: "intel", "volatile");
+}
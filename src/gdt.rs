@@ -0,0 +1,142 @@
+// file:	gdt.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Our own GDT and TSS, so we stop relying on whatever the bootloader
+//			left loaded, and so exceptions can switch to known-good stacks.
+
+// includes
+use lazy_static::lazy_static;
+use x86_64::PrivilegeLevel;
+use x86_64::VirtAddr;
+use x86_64::structures::gdt::{Descriptor, DescriptorFlags,
+								GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::paging::Page;
+use x86_64::structures::tss::TaskStateSegment;
+use crate::arch::{Arch, Segments};
+use crate::memory::stack;
+
+/// IST slot (0-6, in TSS order) that the double fault handler runs on
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+/// IST slot that the NMI handler runs on
+pub const NMI_IST_INDEX: u16 = 1;
+/// IST slot that the machine check handler runs on
+pub const MACHINE_CHECK_IST_INDEX: u16 = 2;
+
+// size of each IST stack. 5 pages is plenty for printing a report.
+const IST_STACK_SIZE: usize = 4096 * 5;
+
+// descriptor bits that the x86_64 crate doesn't have flags for
+const WRITABLE: u64 = 1 << 41;
+const DPL_RING3: u64 = 3 << 45;
+
+// IstStack is a stack with a page below it that init() unmaps, so running
+// off the bottom faults instead of scribbling on whatever's next
+#[repr(C, align(4096))]
+struct IstStack {
+	guard: [u8; 4096],
+	stack: [u8; IST_STACK_SIZE],
+}
+
+// what every IstStack starts out as
+const EMPTY_STACK: IstStack = IstStack {
+	guard: [0; 4096],
+	stack: [0; IST_STACK_SIZE],
+};
+
+// the actual IST stacks. these are static mut because the cpu writes to them
+// behind rust's back; rust itself only ever takes their address.
+static mut DOUBLE_FAULT_STACK: IstStack = EMPTY_STACK;
+static mut NMI_STACK: IstStack = EMPTY_STACK;
+static mut MACHINE_CHECK_STACK: IstStack = EMPTY_STACK;
+// the stack the cpu switches to when an interrupt or system call comes in
+// from ring 3. it's the same kind of stack as the IST ones, it just goes in
+// the TSS's privilege stack table instead.
+static mut RING0_STACK: IstStack = EMPTY_STACK;
+
+// Selectors contains the selectors for every segment in the GDT. the order of
+// the user segments matters: sysret expects user data right before user code.
+pub struct Selectors {
+	pub kernel_code: SegmentSelector,
+	pub kernel_data: SegmentSelector,
+	pub user_data: SegmentSelector,
+	pub user_code: SegmentSelector,
+	pub tss: SegmentSelector,
+}
+
+// lazy_static instances of the TSS and the GDT that points at it
+lazy_static! {
+	static ref TSS: TaskStateSegment = {
+		let mut tss = TaskStateSegment::new();
+		tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] =
+			stack_end(unsafe { &DOUBLE_FAULT_STACK });
+		tss.interrupt_stack_table[NMI_IST_INDEX as usize] =
+			stack_end(unsafe { &NMI_STACK });
+		tss.interrupt_stack_table[MACHINE_CHECK_IST_INDEX as usize] =
+			stack_end(unsafe { &MACHINE_CHECK_STACK });
+		tss.privilege_stack_table[0] = stack_end(unsafe { &RING0_STACK });
+		tss
+	};
+
+	static ref GDT: (GlobalDescriptorTable, Selectors) = {
+		use self::DescriptorFlags as Flags;
+
+		let mut gdt = GlobalDescriptorTable::new();
+		let kernel_code = gdt.add_entry(Descriptor::kernel_code_segment());
+		let kernel_data = gdt.add_entry(Descriptor::UserSegment(
+			(Flags::USER_SEGMENT | Flags::PRESENT).bits() | WRITABLE));
+		let user_data = gdt.add_entry(Descriptor::UserSegment(
+			(Flags::USER_SEGMENT | Flags::PRESENT).bits() | WRITABLE
+				| DPL_RING3));
+		let user_code = gdt.add_entry(Descriptor::UserSegment(
+			(Flags::USER_SEGMENT | Flags::PRESENT | Flags::EXECUTABLE
+				| Flags::LONG_MODE).bits() | DPL_RING3));
+		let tss = gdt.add_entry(Descriptor::tss_segment(&TSS));
+
+		// add_entry() always hands back ring 0 selectors
+		let user_data = SegmentSelector::new(user_data.index(),
+												PrivilegeLevel::Ring3);
+		let user_code = SegmentSelector::new(user_code.index(),
+												PrivilegeLevel::Ring3);
+
+		(gdt, Selectors { kernel_code, kernel_data, user_data, user_code, tss })
+	};
+}
+
+/// stack_end() returns the address of the top of an IST stack, since stacks
+/// grow down and that's what the cpu wants in the TSS
+fn stack_end(stack: &'static IstStack) -> VirtAddr {
+	VirtAddr::from_ptr(&stack.stack) + IST_STACK_SIZE as u64
+}
+
+/// guard_stack() unmaps an IST stack's guard page, and registers it so
+/// overflowing the stack gets reported by name
+fn guard_stack(name: &'static str, stack: &'static IstStack) {
+	let guard = Page::containing_address(VirtAddr::from_ptr(&stack.guard));
+	unsafe {
+		stack::guard_static(name, guard, stack_end(stack))
+			.expect("couldn't guard an IST stack");
+	}
+}
+
+/// selectors() returns the selectors for the segments in our GDT
+pub fn selectors() -> &'static Selectors {
+	&GDT.1
+}
+
+/// init() loads our GDT, reloads the segment registers and loads the TSS,
+/// then unmaps the guard pages under the TSS's stacks. this has to happen
+/// before the IDT gets built, because IDT entries capture the current code
+/// segment.
+pub fn init() {
+	GDT.0.load();
+	unsafe {
+		Arch::load_segments(GDT.1.kernel_code, GDT.1.kernel_data, GDT.1.tss);
+	}
+
+	unsafe {
+		guard_stack("double fault", &DOUBLE_FAULT_STACK);
+		guard_stack("nmi", &NMI_STACK);
+		guard_stack("machine check", &MACHINE_CHECK_STACK);
+		guard_stack("ring 0", &RING0_STACK);
+	}
+}
@@ -0,0 +1,197 @@
+// file:	apic.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Driver for the local APIC and its timer
+
+// includes
+use core::ptr::{read_volatile, write_volatile};
+use spin::Once;
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::registers::model_specific::Msr;
+use crate::acpi::{Madt, Polarity, TriggerMode};
+use crate::cpu::{self, FeatureFlags};
+use crate::memory::mmio::map_mmio;
+use super::pit;
+
+// the MSR with the local APIC's base address and global enable bit
+const IA32_APIC_BASE: u32 = 0x1b;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const APIC_BASE_ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
+
+// register offsets
+const REG_ID: u32 = 0x20;
+const REG_TPR: u32 = 0x80;
+const REG_EOI: u32 = 0xb0;
+const REG_SVR: u32 = 0xf0;
+const REG_ESR: u32 = 0x280;
+const REG_LVT_TIMER: u32 = 0x320;
+const REG_LVT_LINT0: u32 = 0x350;
+const REG_LVT_LINT1: u32 = 0x360;
+const REG_LVT_ERROR: u32 = 0x370;
+const REG_TIMER_INITIAL: u32 = 0x380;
+const REG_TIMER_CURRENT: u32 = 0x390;
+const REG_TIMER_DIVIDE: u32 = 0x3e0;
+
+// register bits
+const SVR_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+const LVT_DELIVERY_NMI: u32 = 0b100 << 8;
+const LVT_ACTIVE_LOW: u32 = 1 << 13;
+const LVT_LEVEL: u32 = 1 << 15;
+const TIMER_DIVIDE_16: u32 = 0b0011;
+
+// how long to let the timer run against the PIT when calibrating
+const CALIBRATION_MS: u32 = 10;
+
+/// vector the local APIC timer fires at
+pub const TIMER_VECTOR: u8 = 0x40;
+/// vector the local APIC reports internal errors at
+pub const ERROR_VECTOR: u8 = 0xfe;
+/// vector the local APIC delivers spurious interrupts at. the low 4 bits
+/// have to be set on older cpus, so 0xff is the traditional choice.
+pub const SPURIOUS_VECTOR: u8 = 0xff;
+
+// LocalApic is the memory-mapped local APIC of the cpu we're running on
+pub struct LocalApic {
+	base: VirtAddr,
+	// timer ticks per millisecond, at a divide of 16
+	ticks_per_ms: u32,
+}
+
+// the bsp's local APIC, once it's been set up
+static LOCAL_APIC: Once<LocalApic> = Once::new();
+
+/// is_present() checks cpuid for an on-chip local APIC
+pub fn is_present() -> bool {
+	cpu::has_feature(FeatureFlags::APIC)
+}
+
+/// local_apic() returns the local APIC, if init() has set it up
+pub fn local_apic() -> Option<&'static LocalApic> {
+	LOCAL_APIC.r#try()
+}
+
+/// init() enables the local APIC, wires up its LINT pins the way the MADT
+/// says, and calibrates its timer against the PIT
+pub unsafe fn init(madt: &Madt) -> &'static LocalApic {
+	LOCAL_APIC.call_once(|| {
+		// the MSR has the real base address, and makes sure it's enabled
+		let mut base_msr = Msr::new(IA32_APIC_BASE);
+		let base = base_msr.read();
+		base_msr.write(base | APIC_BASE_ENABLE);
+
+		let mut apic = LocalApic {
+			base: map_mmio(PhysAddr::new(base & APIC_BASE_ADDR_MASK), 4096),
+			ticks_per_ms: 0,
+		};
+		apic.setup(madt);
+		apic.ticks_per_ms = apic.calibrate_timer();
+		apic
+	})
+}
+
+impl LocalApic {
+	/// read() reads a 32-bit register
+	unsafe fn read(&self, reg: u32) -> u32 {
+		read_volatile((self.base + reg as u64).as_ptr::<u32>())
+	}
+
+	/// write() writes a 32-bit register
+	unsafe fn write(&self, reg: u32, value: u32) {
+		write_volatile((self.base + reg as u64).as_mut_ptr::<u32>(), value);
+	}
+
+	/// setup() puts the local APIC into a known state and software-enables it
+	unsafe fn setup(&self, madt: &Madt) {
+		// everything masked to start with, except errors
+		self.write(REG_LVT_TIMER, LVT_MASKED);
+		self.write(REG_LVT_LINT0, LVT_MASKED);
+		self.write(REG_LVT_LINT1, LVT_MASKED);
+		self.write(REG_LVT_ERROR, ERROR_VECTOR as u32);
+
+		// hook up whichever LINT pins the firmware says carry NMIs
+		let id = self.id();
+		for nmi in madt.local_nmis() {
+			if nmi.processor != 0xff && nmi.processor != id {
+				continue;
+			}
+			let mut lvt = LVT_DELIVERY_NMI;
+			if nmi.polarity == Polarity::ActiveLow {
+				lvt |= LVT_ACTIVE_LOW;
+			}
+			if nmi.trigger == TriggerMode::Level {
+				lvt |= LVT_LEVEL;
+			}
+			let reg = if nmi.lint == 0 { REG_LVT_LINT0 } else { REG_LVT_LINT1 };
+			self.write(reg, lvt);
+		}
+
+		// clear out any errors from before we got here. the ESR has to be
+		// written before it's read
+		self.write(REG_ESR, 0);
+		self.write(REG_ESR, 0);
+
+		// accept every priority, then software-enable the APIC
+		self.write(REG_TPR, 0);
+		self.write(REG_SVR, SVR_ENABLE | SPURIOUS_VECTOR as u32);
+		self.end_of_interrupt();
+	}
+
+	/// calibrate_timer() runs the timer against the PIT to work out how fast
+	/// it ticks, since that depends on the bus clock
+	unsafe fn calibrate_timer(&self) -> u32 {
+		self.write(REG_TIMER_DIVIDE, TIMER_DIVIDE_16);
+		self.write(REG_LVT_TIMER, LVT_MASKED);
+		self.write(REG_TIMER_INITIAL, 0xffff_ffff);
+
+		pit::wait_ms(CALIBRATION_MS);
+
+		let elapsed = 0xffff_ffff - self.read(REG_TIMER_CURRENT);
+		self.write(REG_TIMER_INITIAL, 0);
+		(elapsed / CALIBRATION_MS).max(1)
+	}
+
+	/// id() returns this local APIC's id
+	pub fn id(&self) -> u8 {
+		(unsafe { self.read(REG_ID) } >> 24) as u8
+	}
+
+	/// ticks_per_ms() returns the calibrated timer rate, at a divide of 16
+	pub fn ticks_per_ms(&self) -> u32 {
+		self.ticks_per_ms
+	}
+
+	/// error_status() reads and clears the error status register
+	pub fn error_status(&self) -> u32 {
+		unsafe {
+			self.write(REG_ESR, 0);
+			self.read(REG_ESR)
+		}
+	}
+
+	/// end_of_interrupt() acknowledges the interrupt currently in service
+	pub fn end_of_interrupt(&self) {
+		unsafe { self.write(REG_EOI, 0) };
+	}
+
+	/// start_timer() makes the timer fire TIMER_VECTOR at the given rate
+	pub fn start_timer(&self, hz: u32) {
+		let count = (self.ticks_per_ms as u64 * 1000 / hz.max(1) as u64)
+			.max(1).min(0xffff_ffff) as u32;
+		unsafe {
+			self.write(REG_TIMER_DIVIDE, TIMER_DIVIDE_16);
+			self.write(REG_LVT_TIMER,
+						LVT_TIMER_PERIODIC | TIMER_VECTOR as u32);
+			self.write(REG_TIMER_INITIAL, count);
+		}
+	}
+
+	/// stop_timer() stops and masks the timer
+	pub fn stop_timer(&self) {
+		unsafe {
+			self.write(REG_LVT_TIMER, LVT_MASKED);
+			self.write(REG_TIMER_INITIAL, 0);
+		}
+	}
+}
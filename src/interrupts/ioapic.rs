@@ -0,0 +1,158 @@
+// file:	ioapic.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Driver for I/O APICs, which route external interrupts to local APICs
+
+// includes
+use core::ptr::{read_volatile, write_volatile};
+use spin::Mutex;
+use x86_64::{PhysAddr, VirtAddr};
+use crate::acpi::{Madt, Polarity, TriggerMode, MAX_IO_APICS};
+use crate::memory::mmio::map_mmio;
+
+// the register select and data window, the only two MMIO registers
+const IOREGSEL: u64 = 0x00;
+const IOWIN: u64 = 0x10;
+
+// indirect register indices
+const REG_VERSION: u32 = 0x01;
+const REG_REDIRECTION_BASE: u32 = 0x10;
+
+// redirection entry bits
+const ENTRY_ACTIVE_LOW: u64 = 1 << 13;
+const ENTRY_LEVEL: u64 = 1 << 15;
+const ENTRY_MASKED: u64 = 1 << 16;
+const ENTRY_DELIVERY_NMI: u64 = 0b100 << 8;
+
+// IoApic is a single memory-mapped I/O APIC
+#[derive(Clone, Copy)]
+struct IoApic {
+	base: VirtAddr,
+	gsi_base: u32,
+	entries: u32,
+}
+
+impl IoApic {
+	/// read() reads an indirect register
+	unsafe fn read(&mut self, reg: u32) -> u32 {
+		write_volatile((self.base + IOREGSEL).as_mut_ptr::<u32>(), reg);
+		read_volatile((self.base + IOWIN).as_ptr::<u32>())
+	}
+
+	/// write() writes an indirect register
+	unsafe fn write(&mut self, reg: u32, value: u32) {
+		write_volatile((self.base + IOREGSEL).as_mut_ptr::<u32>(), reg);
+		write_volatile((self.base + IOWIN).as_mut_ptr::<u32>(), value);
+	}
+
+	/// handles_gsi() returns whether a GSI is one of this I/O APIC's inputs
+	fn handles_gsi(&self, gsi: u32) -> bool {
+		self.gsi_base <= gsi && gsi < self.gsi_base + self.entries
+	}
+
+	/// read_entry() reads the redirection entry for one of our inputs
+	unsafe fn read_entry(&mut self, input: u32) -> u64 {
+		let reg = REG_REDIRECTION_BASE + input * 2;
+		(self.read(reg) as u64) | ((self.read(reg + 1) as u64) << 32)
+	}
+
+	/// write_entry() writes the redirection entry for one of our inputs. the
+	/// low half goes last, since that's where the mask bit is.
+	unsafe fn write_entry(&mut self, input: u32, entry: u64) {
+		let reg = REG_REDIRECTION_BASE + input * 2;
+		self.write(reg, ENTRY_MASKED as u32);
+		self.write(reg + 1, (entry >> 32) as u32);
+		self.write(reg, entry as u32);
+	}
+}
+
+// every I/O APIC in the system
+static IO_APICS: Mutex<[Option<IoApic>; MAX_IO_APICS]> =
+	Mutex::new([None; MAX_IO_APICS]);
+
+/// init() maps every I/O APIC in the MADT and masks all of their inputs.
+/// returns false if there weren't any.
+pub unsafe fn init(madt: &Madt) -> bool {
+	let mut io_apics = IO_APICS.lock();
+	let mut found = false;
+
+	for (slot, info) in io_apics.iter_mut().zip(madt.io_apics()) {
+		let mut io_apic = IoApic {
+			base: map_mmio(PhysAddr::new(info.address as u64), 4096),
+			gsi_base: info.gsi_base,
+			entries: 0,
+		};
+		// the version register has the highest entry number in 16-23
+		io_apic.entries = ((io_apic.read(REG_VERSION) >> 16) & 0xff) + 1;
+		for input in 0..io_apic.entries {
+			io_apic.write_entry(input, ENTRY_MASKED);
+		}
+
+		*slot = Some(io_apic);
+		found = true;
+	}
+
+	found
+}
+
+/// RedirectionEntry describes where and how a GSI gets delivered
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectionEntry {
+	pub vector: u8,
+	pub destination: u8,
+	pub polarity: Polarity,
+	pub trigger: TriggerMode,
+	pub nmi: bool,
+}
+
+impl RedirectionEntry {
+	/// bits() encodes the entry the way the I/O APIC wants it. anything that
+	/// says to conform to the bus gets treated as ISA: active high, edge.
+	fn bits(&self) -> u64 {
+		let mut bits = self.vector as u64 | (self.destination as u64) << 56;
+		if self.polarity == Polarity::ActiveLow {
+			bits |= ENTRY_ACTIVE_LOW;
+		}
+		if self.trigger == TriggerMode::Level {
+			bits |= ENTRY_LEVEL;
+		}
+		if self.nmi {
+			bits |= ENTRY_DELIVERY_NMI;
+		}
+		bits
+	}
+}
+
+/// with_gsi() runs f on the I/O APIC that handles a GSI and the GSI's input
+/// number on it. returns None if no I/O APIC handles it.
+fn with_gsi<F, R>(gsi: u32, f: F) -> Option<R>
+	where F: FnOnce(&mut IoApic, u32) -> R
+{
+	let mut io_apics = IO_APICS.lock();
+	io_apics.iter_mut().filter_map(Option::as_mut)
+		.find(|io_apic| io_apic.handles_gsi(gsi))
+		.map(|io_apic| {
+			let input = gsi - io_apic.gsi_base;
+			f(io_apic, input)
+		})
+}
+
+/// route() points a GSI at a redirection entry and unmasks it. returns false
+/// if no I/O APIC handles that GSI.
+pub fn route(gsi: u32, entry: RedirectionEntry) -> bool {
+	with_gsi(gsi, |io_apic, input| unsafe {
+		io_apic.write_entry(input, entry.bits());
+	}).is_some()
+}
+
+/// set_masked() masks or unmasks a GSI without touching the rest of its entry
+pub fn set_masked(gsi: u32, masked: bool) -> bool {
+	with_gsi(gsi, |io_apic, input| unsafe {
+		let entry = io_apic.read_entry(input);
+		if masked {
+			io_apic.write_entry(input, entry | ENTRY_MASKED);
+		} else {
+			io_apic.write_entry(input, entry & !ENTRY_MASKED);
+		}
+	}).is_some()
+}
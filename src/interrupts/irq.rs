@@ -0,0 +1,512 @@
+// file:	irq.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Hardware IRQ dispatch layer, which sits between the IDT and drivers
+//			and hides which interrupt controller is actually in use
+
+// includes
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::{Mutex, Once};
+use crate::acpi::{self, Polarity, TriggerMode};
+use crate::arch::{Arch, InterruptController, Interrupts};
+use crate::println;
+use super::InterruptContext;
+use super::{apic, ioapic, pit, stats};
+use super::ioapic::RedirectionEntry;
+use super::pic::{ChainedPics, PIC_1_OFFSET, PIC_2_OFFSET};
+
+/// number of IRQ lines. 0-15 are the ISA IRQs. with the APIC, 16-23 are the
+/// GSIs past those, which is where PCI interrupts end up.
+pub const IRQ_LINES: u8 = 24;
+/// number of IRQ lines the PIC pair has
+pub const ISA_IRQ_LINES: u8 = 16;
+/// vector IRQ line 0 fires at. the lines are contiguous from here.
+pub const IRQ_BASE_VECTOR: u8 = PIC_1_OFFSET;
+
+/// most handlers that can share one vector
+pub const MAX_SHARED_HANDLERS: usize = 4;
+
+/// IrqReturn is what a handler says about an IRQ. on a shared line, it's how
+/// each handler says whether its device was the one that fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqReturn {
+	Handled,
+	NotHandled,
+}
+
+/// IrqHandler is what a driver hands us to get called when its line fires.
+/// the end of interrupt gets sent for it afterwards.
+pub type IrqHandler = fn(&mut InterruptContext) -> IrqReturn;
+
+/// IrqError is why registering or unregistering a handler failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+	// the line doesn't exist on the controller in use
+	InvalidLine(u8),
+	// someone already has the line to themselves
+	AlreadyExclusive(u8),
+	// someone asked for the line to themselves, but it already has handlers
+	AlreadyShared(u8),
+	// the line already has MAX_SHARED_HANDLERS handlers
+	LineFull(u8),
+	// the handler isn't registered on that line
+	NotRegistered(u8),
+	// the controller in use can't deliver the line as an NMI
+	NmiUnsupported(u8),
+}
+
+// VectorHandlers is the dispatch table entry for a single vector
+#[derive(Clone, Copy)]
+struct VectorHandlers {
+	handlers: [Option<IrqHandler>; MAX_SHARED_HANDLERS],
+	exclusive: bool,
+}
+
+impl VectorHandlers {
+	/// is_empty() returns whether nothing is registered on this vector
+	fn is_empty(&self) -> bool {
+		self.handlers.iter().all(Option::is_none)
+	}
+}
+
+/// Controller is the interrupt controller that IRQs are coming through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+	Pic,
+	Apic,
+}
+
+impl InterruptController for Controller {
+	/// line_count() returns how many lines the controller has
+	fn line_count(&self) -> u8 {
+		match self {
+			Controller::Pic => ISA_IRQ_LINES,
+			Controller::Apic => IRQ_LINES,
+		}
+	}
+
+	/// enable_line() unmasks a line. on the APIC, that means routing its GSI
+	/// through the I/O APIC to the line's vector.
+	fn enable_line(&self, line: u8) {
+		match self {
+			Controller::Pic => {
+				Arch::without_interrupts(|| unsafe {
+					PICS.lock().unmask(line)
+				});
+			},
+			Controller::Apic => {
+				let (gsi, polarity, trigger) = line_routing(line);
+				let destination = apic::local_apic()
+					.expect("APIC in use but not initialized").id();
+				let routed = ioapic::route(gsi, RedirectionEntry {
+					vector: IRQ_BASE_VECTOR + line,
+					destination,
+					polarity,
+					trigger,
+					nmi: false,
+				});
+				assert!(routed, "no I/O APIC handles GSI {}", gsi);
+			},
+		}
+	}
+
+	/// disable_line() masks a line
+	fn disable_line(&self, line: u8) {
+		match self {
+			Controller::Pic => {
+				Arch::without_interrupts(|| unsafe { PICS.lock().mask(line) });
+			},
+			Controller::Apic => {
+				ioapic::set_masked(line_routing(line).0, true);
+			},
+		}
+	}
+
+	/// end_of_interrupt() acknowledges a line
+	fn end_of_interrupt(&self, line: u8) {
+		match self {
+			Controller::Pic => unsafe { PICS.lock().end_of_interrupt(line) },
+			Controller::Apic => {
+				if let Some(local_apic) = apic::local_apic() {
+					local_apic.end_of_interrupt();
+				}
+			},
+		}
+	}
+}
+
+/// ControllerPreference picks which controller init_with() tries to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerPreference {
+	// the APIC if there's a usable one, otherwise the PIC
+	Auto,
+	// always the PIC, even if there's an APIC
+	Pic,
+	// the APIC, complaining if we have to fall back to the PIC
+	Apic,
+}
+
+// the PIC pair. every lock of this outside of an IRQ has to have interrupts
+// disabled, or an IRQ on the same cpu would spin on it forever.
+static PICS: Mutex<ChainedPics> =
+	Mutex::new(ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET));
+
+// the controller that got picked at init
+static CONTROLLER: Once<Controller> = Once::new();
+
+// the handlers registered on each vector
+static DISPATCH_TABLE: Mutex<[VectorHandlers; 256]> =
+	Mutex::new([VectorHandlers {
+		handlers: [None; MAX_SHARED_HANDLERS],
+		exclusive: false,
+	}; 256]);
+
+// LineMode is a polarity and trigger mode override for a line
+type LineMode = (Polarity, TriggerMode);
+
+// the override for each line, for the APIC
+static LINE_MODES: Mutex<[Option<LineMode>; IRQ_LINES as usize]> =
+	Mutex::new([None; IRQ_LINES as usize]);
+
+// how many spurious IRQs the controllers have thrown at us
+static SPURIOUS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// how many IRQs have been dispatched, which the watchdog uses to tell that
+// the cpu is still taking interrupts
+static DISPATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// init() sets up the preferred interrupt controller, falling back to the
+/// PIC if there's no usable APIC. every line starts out masked.
+pub fn init(preference: ControllerPreference) {
+	Arch::without_interrupts(|| {
+		// the PICs get remapped even when we're going to mask them, so
+		// anything they fire anyway doesn't look like an exception
+		unsafe { PICS.lock().initialize() };
+
+		let controller = match preference {
+			ControllerPreference::Pic => Controller::Pic,
+			_ if unsafe { init_apic() } => Controller::Apic,
+			ControllerPreference::Apic => {
+				println!("no usable APIC, falling back to the PIC");
+				Controller::Pic
+			},
+			ControllerPreference::Auto => Controller::Pic,
+		};
+
+		if controller == Controller::Apic {
+			unsafe { PICS.lock().mask_all() };
+		}
+		CONTROLLER.call_once(|| controller);
+	});
+}
+
+/// init_apic() brings up the local and I/O APICs. it needs both cpuid and
+/// the MADT to agree there's an APIC, since we can't route IRQs without
+/// knowing where the I/O APICs are.
+unsafe fn init_apic() -> bool {
+	if !apic::is_present() {
+		return false;
+	}
+	let madt = match acpi::madt() {
+		Some(madt) => madt,
+		None => return false,
+	};
+	if !ioapic::init(madt) {
+		return false;
+	}
+
+	apic::init(madt);
+	true
+}
+
+/// controller() returns the interrupt controller that's in use
+pub fn controller() -> Controller {
+	*CONTROLLER.r#try().unwrap_or(&Controller::Pic)
+}
+
+/// line_exists() returns whether a line exists on the current controller
+fn line_exists(line: u8) -> bool {
+	line < controller().line_count()
+}
+
+/// check_line() makes sure a line exists on the current controller
+fn check_line(line: u8) {
+	assert!(line_exists(line), "IRQ line {} doesn't exist on the {:?}",
+			line, controller());
+}
+
+/// line_routing() works out which GSI a line is, and how it's triggered.
+/// ISA lines can be moved around by the MADT, and default to active high and
+/// edge triggered. the rest are PCI, so they default to active low and level.
+fn line_routing(line: u8) -> (u32, Polarity, TriggerMode) {
+	let (gsi, polarity, trigger) = if line < ISA_IRQ_LINES {
+		match acpi::madt().and_then(|madt| madt.isa_override(line)) {
+			Some(entry) => (entry.gsi, entry.polarity, entry.trigger),
+			None => (line as u32, Polarity::ActiveHigh, TriggerMode::Edge),
+		}
+	} else {
+		(line as u32, Polarity::ActiveLow, TriggerMode::Level)
+	};
+
+	// conforming to the bus means ISA's defaults, and drivers get the last
+	// word over the firmware
+	let polarity = match polarity {
+		Polarity::ConformToBus => Polarity::ActiveHigh,
+		polarity => polarity,
+	};
+	let trigger = match trigger {
+		TriggerMode::ConformToBus => TriggerMode::Edge,
+		trigger => trigger,
+	};
+	match LINE_MODES.lock()[line as usize] {
+		Some((polarity, trigger)) => (gsi, polarity, trigger),
+		None => (gsi, polarity, trigger),
+	}
+}
+
+/// configure_irq() overrides a line's polarity and trigger mode. only the
+/// APIC cares; ISA lines on the PIC are always active high and edge.
+/// takes effect the next time the line is enabled.
+pub fn configure_irq(line: u8, polarity: Polarity, trigger: TriggerMode) {
+	check_line(line);
+	Arch::without_interrupts(|| {
+		LINE_MODES.lock()[line as usize] = Some((polarity, trigger));
+	});
+}
+
+/// enable_irq() unmasks an IRQ line so it can start firing
+pub fn enable_irq(line: u8) {
+	check_line(line);
+	controller().enable_line(line);
+}
+
+/// disable_irq() masks an IRQ line so it stops firing
+pub fn disable_irq(line: u8) {
+	check_line(line);
+	controller().disable_line(line);
+}
+
+/// register_vector() adds a handler to a vector's dispatch table. returns
+/// whether it was the first handler on the vector.
+fn register_vector(vector: u8, handler: IrqHandler, exclusive: bool)
+	-> Result<bool, IrqError>
+{
+	let line = vector.wrapping_sub(IRQ_BASE_VECTOR);
+	Arch::without_interrupts(|| {
+		let mut table = DISPATCH_TABLE.lock();
+		let entry = &mut table[vector as usize];
+		let first = entry.is_empty();
+
+		if entry.exclusive && !first {
+			return Err(IrqError::AlreadyExclusive(line));
+		}
+		if exclusive && !first {
+			return Err(IrqError::AlreadyShared(line));
+		}
+
+		let slot = entry.handlers.iter_mut().find(|slot| slot.is_none())
+			.ok_or(IrqError::LineFull(line))?;
+		*slot = Some(handler);
+		entry.exclusive = exclusive;
+		Ok(first)
+	})
+}
+
+/// unregister_vector() removes a handler from a vector's dispatch table.
+/// returns whether the vector is empty now.
+fn unregister_vector(vector: u8, handler: IrqHandler)
+	-> Result<bool, IrqError>
+{
+	let line = vector.wrapping_sub(IRQ_BASE_VECTOR);
+	Arch::without_interrupts(|| {
+		let mut table = DISPATCH_TABLE.lock();
+		let entry = &mut table[vector as usize];
+
+		// fn pointers compare by address, which is what we want here
+		let slot = entry.handlers.iter_mut()
+			.find(|slot| slot.map(|registered| registered as usize)
+							== Some(handler as usize))
+			.ok_or(IrqError::NotRegistered(line))?;
+		*slot = None;
+
+		if entry.is_empty() {
+			entry.exclusive = false;
+		}
+		Ok(entry.is_empty())
+	})
+}
+
+/// register_irq() adds a handler to a line, which other handlers can share.
+/// the line gets enabled when its first handler is registered.
+pub fn register_irq(line: u8, handler: IrqHandler) -> Result<(), IrqError> {
+	if !line_exists(line) {
+		return Err(IrqError::InvalidLine(line));
+	}
+	if register_vector(IRQ_BASE_VECTOR + line, handler, false)? {
+		enable_irq(line);
+	}
+	Ok(())
+}
+
+/// register_irq_exclusive() claims a line for a single handler, for devices
+/// that can't tell whether they were the ones that fired
+pub fn register_irq_exclusive(line: u8, handler: IrqHandler)
+	-> Result<(), IrqError>
+{
+	if !line_exists(line) {
+		return Err(IrqError::InvalidLine(line));
+	}
+	register_vector(IRQ_BASE_VECTOR + line, handler, true)?;
+	enable_irq(line);
+	Ok(())
+}
+
+/// unregister_irq() removes a handler from a line. the line gets disabled
+/// once its last handler is gone.
+pub fn unregister_irq(line: u8, handler: IrqHandler) -> Result<(), IrqError> {
+	if !line_exists(line) {
+		return Err(IrqError::InvalidLine(line));
+	}
+	if unregister_vector(IRQ_BASE_VECTOR + line, handler)? {
+		disable_irq(line);
+	}
+	Ok(())
+}
+
+/// start_timer() calls handler at (roughly) the given rate, using the local
+/// APIC timer if there is one and the PIT on IRQ 0 otherwise. either way,
+/// the timer belongs to handler alone, and if someone else already has it
+/// it's left running as they set it up.
+pub fn start_timer(hz: u32, handler: IrqHandler) -> Result<(), IrqError> {
+	match controller() {
+		Controller::Pic => {
+			register_irq_exclusive(0, handler)?;
+			pit::set_periodic(hz);
+			Ok(())
+		},
+		Controller::Apic => {
+			register_vector(apic::TIMER_VECTOR, handler, true)?;
+			apic::local_apic().expect("APIC in use but not initialized")
+				.start_timer(hz);
+			Ok(())
+		},
+	}
+}
+
+/// nmi_placeholder() holds a line that's been routed as an NMI, so nothing
+/// else can register on it. it never actually gets called.
+fn nmi_placeholder(_context: &mut InterruptContext) -> IrqReturn {
+	IrqReturn::NotHandled
+}
+
+/// route_nmi() claims a line for itself and has it delivered as an NMI
+/// instead of an IRQ. only the I/O APIC can do that.
+pub(super) fn route_nmi(line: u8) -> Result<(), IrqError> {
+	if !line_exists(line) {
+		return Err(IrqError::InvalidLine(line));
+	}
+	if controller() != Controller::Apic {
+		return Err(IrqError::NmiUnsupported(line));
+	}
+	register_vector(IRQ_BASE_VECTOR + line, nmi_placeholder, true)?;
+
+	// the vector is ignored for NMIs, and they have to be edge triggered
+	let (gsi, polarity, _) = line_routing(line);
+	let destination = apic::local_apic()
+		.expect("APIC in use but not initialized").id();
+	let routed = ioapic::route(gsi, RedirectionEntry {
+		vector: 0,
+		destination,
+		polarity,
+		trigger: TriggerMode::Edge,
+		nmi: true,
+	});
+	assert!(routed, "no I/O APIC handles GSI {}", gsi);
+	Ok(())
+}
+
+/// release_nmi() undoes route_nmi(), masking the line again
+pub(super) fn release_nmi(line: u8) -> Result<(), IrqError> {
+	unregister_irq(line, nmi_placeholder)
+}
+
+/// dispatch_count() returns how many IRQs have been dispatched so far
+pub(super) fn dispatch_count() -> usize {
+	DISPATCH_COUNT.load(Ordering::Relaxed)
+}
+
+/// spurious_count() returns how many spurious IRQs have been ignored
+pub fn spurious_count() -> usize {
+	SPURIOUS_COUNT.load(Ordering::Relaxed)
+}
+
+/// end_of_interrupt() acknowledges a line on whichever controller is in use
+fn end_of_interrupt(line: u8) {
+	controller().end_of_interrupt(line);
+}
+
+/// dispatch() is called from the IDT for every IRQ line vector. it weeds out
+/// spurious IRQs, calls the line's handler and acknowledges the IRQ.
+pub(super) fn dispatch(vector: u8, context: &mut InterruptContext) {
+	let line = vector - IRQ_BASE_VECTOR;
+
+	// spurious PIC IRQs mustn't get a normal end of interrupt. the APIC
+	// has its own vector for those.
+	if controller() == Controller::Pic {
+		let mut pics = PICS.lock();
+		if unsafe { pics.is_spurious(line) } {
+			SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+			stats::record_spurious(vector);
+			unsafe { pics.end_of_spurious_interrupt(line) };
+			return;
+		}
+	}
+
+	if !run_handlers(vector, context) {
+		stats::record_unhandled(vector);
+	}
+	end_of_interrupt(line);
+}
+
+/// dispatch_timer() is called from the IDT for the local APIC timer
+pub(super) fn dispatch_timer(context: &mut InterruptContext) {
+	if !run_handlers(apic::TIMER_VECTOR, context) {
+		stats::record_unhandled(apic::TIMER_VECTOR);
+	}
+
+	if let Some(local_apic) = apic::local_apic() {
+		local_apic.end_of_interrupt();
+	}
+}
+
+/// run_handlers() calls every handler registered on a vector. they all get a
+/// look, since more than one device on a shared line can fire at once.
+/// returns whether any of them handled it.
+fn run_handlers(vector: u8, context: &mut InterruptContext) -> bool {
+	DISPATCH_COUNT.fetch_add(1, Ordering::Relaxed);
+
+	// copy the handlers out so that they can run without the lock held
+	let handlers = DISPATCH_TABLE.lock()[vector as usize].handlers;
+
+	let mut handled = false;
+	for handler in handlers.iter().filter_map(|handler| *handler) {
+		if handler(context) == IrqReturn::Handled {
+			handled = true;
+		}
+	}
+	handled
+}
+
+/// apic_spurious() is called from the IDT for the local APIC's spurious
+/// vector. those don't get an end of interrupt.
+pub(super) fn apic_spurious() {
+	SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// apic_error() is called from the IDT when the local APIC reports an error
+pub(super) fn apic_error() {
+	if let Some(local_apic) = apic::local_apic() {
+		println!("APIC error: {:#x}", local_apic.error_status());
+		local_apic.end_of_interrupt();
+	}
+}
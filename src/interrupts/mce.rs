@@ -0,0 +1,291 @@
+// file:	mce.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Machine check architecture support. uncorrected errors come in as
+//			#MC, and corrected ones get picked up by polling the banks.
+
+// includes
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use x86_64::registers::model_specific::Msr;
+use crate::{emergency_println, fatal_println, println};
+use crate::cpu::{self, FeatureFlags};
+use super::report;
+use super::InterruptContext;
+
+// the global machine check MSRs
+const IA32_MCG_CAP: u32 = 0x179;
+const IA32_MCG_STATUS: u32 = 0x17a;
+const IA32_MCG_CTL: u32 = 0x17b;
+// each bank has 4 MSRs, CTL, STATUS, ADDR and MISC, starting here
+const IA32_MC0_CTL: u32 = 0x400;
+
+// MCG_CAP bits
+const MCG_COUNT_MASK: u64 = 0xff;
+const MCG_CTL_P: u64 = 1 << 8;
+
+// MCG_STATUS bits
+const MCG_RIPV: u64 = 1 << 0;
+const MCG_EIPV: u64 = 1 << 1;
+const MCG_MCIP: u64 = 1 << 2;
+
+// MCi_STATUS bits
+const STATUS_VAL: u64 = 1 << 63;
+const STATUS_OVER: u64 = 1 << 62;
+const STATUS_UC: u64 = 1 << 61;
+const STATUS_EN: u64 = 1 << 60;
+const STATUS_MISCV: u64 = 1 << 59;
+const STATUS_ADDRV: u64 = 1 << 58;
+const STATUS_PCC: u64 = 1 << 57;
+
+// the CR4 bit that lets #MC be delivered at all
+const CR4_MCE: u64 = 1 << 6;
+
+// how many banks there are, or 0 if there's no MCA
+static BANK_COUNT: AtomicUsize = AtomicUsize::new(0);
+// how many corrected errors polling has found
+static CORRECTED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// ErrorClass is what the architectural part of an MCA error code says
+/// went wrong. the cache, TLB and bus errors say at what level, where 3
+/// means the cpu didn't say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+	NoError,
+	Unclassified,
+	MicrocodeRomParity,
+	External,
+	Frc,
+	InternalParity,
+	InternalTimer,
+	InternalUnclassified,
+	GenericCache { level: u8 },
+	Tlb { level: u8 },
+	MemoryController,
+	Cache { level: u8 },
+	Bus { level: u8 },
+	Unknown(u16),
+}
+
+impl ErrorClass {
+	/// decode() decodes the low 16 bits of an MCi_STATUS. the simple codes
+	/// have to match exactly, and the compound ones by pattern, ignoring the
+	/// corrected error filtering bit.
+	pub fn decode(code: u16) -> ErrorClass {
+		match code {
+			0x0000 => return ErrorClass::NoError,
+			0x0001 => return ErrorClass::Unclassified,
+			0x0002 => return ErrorClass::MicrocodeRomParity,
+			0x0003 => return ErrorClass::External,
+			0x0004 => return ErrorClass::Frc,
+			0x0005 => return ErrorClass::InternalParity,
+			0x0400 => return ErrorClass::InternalTimer,
+			_ => {},
+		}
+		if code & 0xfc00 == 0x0400 {
+			return ErrorClass::InternalUnclassified;
+		}
+
+		let compound = code & !(1 << 12);
+		let level = (compound & 0b11) as u8;
+		if compound & 0xfffc == 0x000c {
+			ErrorClass::GenericCache { level }
+		} else if compound & 0xfff0 == 0x0010 {
+			ErrorClass::Tlb { level }
+		} else if compound & 0xff80 == 0x0080 {
+			ErrorClass::MemoryController
+		} else if compound & 0xff00 == 0x0100 {
+			ErrorClass::Cache { level }
+		} else if compound & 0xf800 == 0x0800 {
+			ErrorClass::Bus { level }
+		} else {
+			ErrorClass::Unknown(code)
+		}
+	}
+}
+
+/// MceRecord is everything one bank had to say about an error
+#[derive(Debug, Clone, Copy)]
+pub struct MceRecord {
+	pub bank: usize,
+	pub status: u64,
+	// only there if the bank said they're valid
+	pub address: Option<u64>,
+	pub misc: Option<u64>,
+	pub class: ErrorClass,
+}
+
+impl MceRecord {
+	/// uncorrected() returns whether the error wasn't corrected
+	pub fn uncorrected(&self) -> bool {
+		self.status & STATUS_UC != 0
+	}
+
+	/// context_corrupt() returns whether the cpu's state can't be trusted
+	/// after the error, which means there's no recovering from it
+	pub fn context_corrupt(&self) -> bool {
+		self.status & STATUS_PCC != 0
+	}
+
+	/// overflowed() returns whether more errors happened than got recorded
+	pub fn overflowed(&self) -> bool {
+		self.status & STATUS_OVER != 0
+	}
+}
+
+impl fmt::Display for MceRecord {
+	/// fmt() prints a record on a single line
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "bank {}: {:?} status {:#018x}{}{}{}{}", self.bank,
+				self.class, self.status,
+				if self.uncorrected() { " uncorrected" } else { " corrected" },
+				if self.context_corrupt() { " pcc" } else { "" },
+				if self.overflowed() { " overflow" } else { "" },
+				if self.status & STATUS_EN != 0 { " signaled" } else { "" })?;
+		if let Some(address) = self.address {
+			write!(f, " addr {:#x}", address)?;
+		}
+		if let Some(misc) = self.misc {
+			write!(f, " misc {:#x}", misc)?;
+		}
+		Ok(())
+	}
+}
+
+/// init() turns on machine checks, if the cpu has them. every bank gets
+/// enabled and has whatever it logged before we got here cleared out.
+pub fn init() {
+	if !cpu::has_feature(FeatureFlags::MCE) {
+		return;
+	}
+
+	if cpu::has_feature(FeatureFlags::MCA) {
+		let cap = unsafe { Msr::new(IA32_MCG_CAP).read() };
+		let banks = (cap & MCG_COUNT_MASK) as usize;
+		unsafe {
+			if cap & MCG_CTL_P != 0 {
+				Msr::new(IA32_MCG_CTL).write(!0);
+			}
+			for bank in 0..banks {
+				Msr::new(bank_msr(bank, 0)).write(!0);
+				Msr::new(bank_msr(bank, 1)).write(0);
+			}
+		}
+		BANK_COUNT.store(banks, Ordering::SeqCst);
+	}
+
+	unsafe {
+		let cr4: u64;
+		asm!("mov $0, cr4" : "=r"(cr4) ::: "intel", "volatile");
+		asm!("mov cr4, $0" :: "r"(cr4 | CR4_MCE) :: "intel", "volatile");
+	}
+}
+
+/// bank_msr() returns the MSR for one of a bank's registers. register is 0
+/// for CTL, 1 for STATUS, 2 for ADDR and 3 for MISC.
+fn bank_msr(bank: usize, register: u32) -> u32 {
+	IA32_MC0_CTL + bank as u32 * 4 + register
+}
+
+/// read_bank() reads a bank's error, if it has one, and clears it
+fn read_bank(bank: usize) -> Option<MceRecord> {
+	let status = unsafe { Msr::new(bank_msr(bank, 1)).read() };
+	if status & STATUS_VAL == 0 {
+		return None;
+	}
+
+	let address = if status & STATUS_ADDRV != 0 {
+		Some(unsafe { Msr::new(bank_msr(bank, 2)).read() })
+	} else {
+		None
+	};
+	let misc = if status & STATUS_MISCV != 0 {
+		Some(unsafe { Msr::new(bank_msr(bank, 3)).read() })
+	} else {
+		None
+	};
+	unsafe { Msr::new(bank_msr(bank, 1)).write(0) };
+
+	Some(MceRecord {
+		bank,
+		status,
+		address,
+		misc,
+		class: ErrorClass::decode(status as u16),
+	})
+}
+
+/// poll_machine_checks() logs and clears any corrected errors the banks have
+/// picked up. uncorrected ones get left for the #MC handler. meant to be
+/// called periodically, and returns how many errors it found.
+pub fn poll_machine_checks() -> usize {
+	let mut found = 0;
+	for bank in 0..BANK_COUNT.load(Ordering::SeqCst) {
+		let status = unsafe { Msr::new(bank_msr(bank, 1)).read() };
+		if status & STATUS_VAL == 0 || status & STATUS_UC != 0 {
+			continue;
+		}
+		if let Some(record) = read_bank(bank) {
+			println!("machine check: {}", record);
+			found += 1;
+		}
+	}
+	CORRECTED_COUNT.fetch_add(found, Ordering::Relaxed);
+	found
+}
+
+/// machine_check_banks() returns how many error reporting banks init()
+/// turned on, or 0 if there's no MCA
+pub fn machine_check_banks() -> usize {
+	BANK_COUNT.load(Ordering::SeqCst)
+}
+
+/// corrected_error_count() returns how many corrected errors polling has
+/// found so far
+pub fn corrected_error_count() -> usize {
+	CORRECTED_COUNT.load(Ordering::Relaxed)
+}
+
+/// handle() is called from the IDT for #MC. it reports every bank with an
+/// error in it, then either resumes or goes down with a full report,
+/// depending on whether the cpu says it's safe to carry on. it works out
+/// which before printing anything, since only a check that isn't going to
+/// return is allowed to break the output locks.
+pub(super) fn handle(context: &mut InterruptContext) {
+	let mcg_status = unsafe { Msr::new(IA32_MCG_STATUS).read() };
+	let banks = BANK_COUNT.load(Ordering::SeqCst);
+	let fatal = mcg_status & MCG_RIPV == 0 || (0..banks).any(|bank| {
+		let status = unsafe { Msr::new(bank_msr(bank, 1)).read() };
+		let corrupt = STATUS_VAL | STATUS_UC | STATUS_PCC;
+		status & corrupt == corrupt
+	});
+
+	// mce_println! picks the output path for whichever way this is going
+	macro_rules! mce_println {
+		($($arg:tt)*) => {
+			if fatal {
+				fatal_println!($($arg)*);
+			} else {
+				emergency_println!($($arg)*);
+			}
+		}
+	}
+
+	mce_println!("\nMACHINE CHECK! mcg_status {:#x}{}{}", mcg_status,
+					if mcg_status & MCG_RIPV != 0 { " ripv" } else { "" },
+					if mcg_status & MCG_EIPV != 0 { " eipv" } else { "" });
+	for bank in 0..banks {
+		if let Some(record) = read_bank(bank) {
+			mce_println!("machine check: {}", record);
+		}
+	}
+
+	if fatal {
+		report::die(format_args!("MACHINE CHECK! unrecoverable"), 18,
+					context);
+	}
+
+	// another #MC while MCIP is set shuts the cpu down, so clear it once
+	// we're done
+	unsafe { Msr::new(IA32_MCG_STATUS).write(mcg_status & !MCG_MCIP) };
+}
@@ -3,10 +3,84 @@
 // date:	2/4/2019
 // desc:	Generic interrupts module which wraps multiple architectures
 
+// includes
+use spin::Mutex;
+use crate::arch::{Arch, Interrupts};
+
 // declare the submodules
+mod apic;
+mod ioapic;
+mod irq;
+mod mce;
+mod nmi;
+mod pic;
+mod pit;
+mod report;
+mod stats;
+
+// the IDT and the handlers behind it, for whichever architecture arch picked
+#[cfg(target_arch = "x86_64")]
 mod x86;
+#[cfg(target_arch = "x86_64")]
+use self::x86 as backend;
+
+// re-export the parts drivers need
+pub use crate::acpi::{Polarity, TriggerMode};
+pub use self::apic::TIMER_VECTOR;
+pub use self::irq::{configure_irq, controller, disable_irq, enable_irq,
+					register_irq, register_irq_exclusive, spurious_count,
+					start_timer, unregister_irq, Controller,
+					ControllerPreference, IrqError, IrqHandler, IrqReturn,
+					IRQ_BASE_VECTOR, IRQ_LINES, ISA_IRQ_LINES,
+					MAX_SHARED_HANDLERS};
+pub use self::mce::{corrected_error_count, machine_check_banks,
+					poll_machine_checks, ErrorClass, MceRecord};
+pub use self::nmi::{start_watchdog, stop_watchdog, WATCHDOG_HZ};
+pub use self::pit::wait_ms;
+pub use self::stats::{dump_interrupts, vector_stats, VectorStats};
+pub use self::backend::{ExceptionStackFrame, InterruptContext,
+						PageFaultErrorCode, Registers, ScratchRegisters};
 
-/// init() initializes the interrupt interface
+// hook that gets called after a fatal exception has been reported
+static FATAL_HOOK: Mutex<Option<fn(u8)>> = Mutex::new(None);
+
+/// init() initializes the interrupt interface, using the APIC if there's a
+/// usable one and the PIC otherwise
 pub fn init() {
-	x86::init_idt();
+	init_with(ControllerPreference::Auto);
+}
+
+/// init_with() initializes the interrupt interface with a specific interrupt
+/// controller. the boot stack gets its guard registered first, so that even
+/// an overflow this early is reported as one, and the interrupt counters get
+/// set up before any handler can touch them. then the GDT, since the IDT
+/// entries capture the code segment and the IST stacks live in the TSS.
+/// every IRQ line starts out masked, so it's safe to turn interrupts on at
+/// the end.
+pub fn init_with(preference: ControllerPreference) {
+	crate::memory::stack::init();
+	stats::init();
+	crate::gdt::init();
+	backend::init_idt();
+	mce::init();
+	crate::fpu::init();
+	crate::syscall::init();
+	irq::init(preference);
+	Arch::enable_interrupts();
+}
+
+/// set_fatal_hook() registers a function that gets called with the vector
+/// number once a fatal exception has been reported. the kernel still hangs
+/// afterwards, so this is mostly for integration tests to report and exit.
+pub fn set_fatal_hook(hook: fn(u8)) {
+	*FATAL_HOOK.lock() = Some(hook);
+}
+
+/// run_fatal_hook() calls the fatal hook, if there is one
+fn run_fatal_hook(vector: u8) {
+	// copy the hook out so we don't hold the lock while it runs
+	let hook = *FATAL_HOOK.lock();
+	if let Some(hook) = hook {
+		hook(vector);
+	}
 }
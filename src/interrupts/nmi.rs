@@ -0,0 +1,117 @@
+// file:	nmi.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	NMI handling, and the hard lockup watchdog that runs off of NMIs
+
+// includes
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use crate::arch::{Arch, ContextFrame, PortIo};
+use crate::emergency_println;
+use super::{irq, pit, report};
+use super::irq::IrqError;
+use super::InterruptContext;
+
+/// rate the watchdog checks on the cpu at. it's about as slow as the PIT
+/// can go.
+pub const WATCHDOG_HZ: u32 = 20;
+
+// the watchdog's NMIs come from the PIT, on IRQ 0
+const WATCHDOG_LINE: u8 = 0;
+
+// port 0x61 bits that say whether the chipset raised the NMI, and why
+const NMI_STATUS_PORT: u16 = 0x61;
+const NMI_SERR: u8 = 1 << 7;
+const NMI_IOCHK: u8 = 1 << 6;
+
+// whether the watchdog is running
+static WATCHDOG_ARMED: AtomicBool = AtomicBool::new(false);
+// how many stuck samples in a row count as a lockup
+static WATCHDOG_LIMIT: AtomicU32 = AtomicU32::new(0);
+// how many stuck samples in a row we've seen so far
+static STUCK_SAMPLES: AtomicU32 = AtomicU32::new(0);
+// the IRQ dispatch count as of the last sample
+static LAST_DISPATCH: AtomicUsize = AtomicUsize::new(0);
+// the rate the PIT was at before the watchdog took it over, 0 if stopped
+static PREVIOUS_HZ: AtomicU32 = AtomicU32::new(0);
+
+/// start_watchdog() starts checking on the cpu WATCHDOG_HZ times a second.
+/// if it's had interrupts off and taken no IRQs for timeout_ms, that's a
+/// hard lockup, and it gets reported like a fatal exception. the PIT's
+/// IRQ 0 gets turned into an NMI for this, so it only works on the APIC,
+/// and only while nothing else has IRQ 0. if IRQ 0 is already someone's
+/// tick source, this fails before the PIT gets touched.
+pub fn start_watchdog(timeout_ms: u32) -> Result<(), IrqError> {
+	let samples = (timeout_ms as u64 * WATCHDOG_HZ as u64 / 1000).max(2);
+	WATCHDOG_LIMIT.store(samples as u32, Ordering::SeqCst);
+	STUCK_SAMPLES.store(0, Ordering::SeqCst);
+	LAST_DISPATCH.store(irq::dispatch_count(), Ordering::SeqCst);
+
+	// armed first, so the first NMI doesn't get reported as a mystery.
+	// route_nmi() claims IRQ 0 for itself, so it's what fails if someone
+	// else is using the PIT.
+	WATCHDOG_ARMED.store(true, Ordering::SeqCst);
+	if let Err(err) = irq::route_nmi(WATCHDOG_LINE) {
+		WATCHDOG_ARMED.store(false, Ordering::SeqCst);
+		return Err(err);
+	}
+	PREVIOUS_HZ.store(pit::periodic_rate(), Ordering::SeqCst);
+	pit::set_periodic(WATCHDOG_HZ);
+	Ok(())
+}
+
+/// stop_watchdog() stops the watchdog, puts the PIT back the way it was
+/// before start_watchdog(), and hands IRQ 0 back
+pub fn stop_watchdog() -> Result<(), IrqError> {
+	irq::release_nmi(WATCHDOG_LINE)?;
+	WATCHDOG_ARMED.store(false, Ordering::SeqCst);
+	match PREVIOUS_HZ.swap(0, Ordering::SeqCst) {
+		0 => pit::stop(),
+		hz => pit::set_periodic(hz),
+	}
+	Ok(())
+}
+
+/// handle() is called from the IDT for every NMI. it runs on its own IST
+/// stack, and can interrupt anything at all, including code holding the
+/// output locks. everything but a lockup goes back to that code, so those
+/// messages go through the emergency path, which never breaks a lock. a
+/// lockup goes down through die(), which does.
+pub(super) fn handle(context: &mut InterruptContext) {
+	// the chipset says in port 0x61 if it was the one that raised the NMI
+	let status = unsafe { Arch::read_port(NMI_STATUS_PORT) };
+	if status & NMI_SERR != 0 {
+		emergency_println!("NMI! system error (port 0x61: {:#x})", status);
+	}
+	if status & NMI_IOCHK != 0 {
+		emergency_println!("NMI! I/O channel check (port 0x61: {:#x})",
+							status);
+	}
+
+	// there's no telling the watchdog's NMIs apart from the rest, like the
+	// ones qemu's monitor sends, so only complain when it's off
+	if WATCHDOG_ARMED.load(Ordering::SeqCst) {
+		check_lockup(context);
+	} else if status & (NMI_SERR | NMI_IOCHK) == 0 {
+		emergency_println!("NMI! unknown source, at {:#x}",
+							context.instruction_pointer());
+	}
+}
+
+/// check_lockup() takes one watchdog sample. the cpu counts as stuck if it
+/// has interrupts off and hasn't dispatched an IRQ since the last sample.
+fn check_lockup(context: &InterruptContext) {
+	let dispatched = irq::dispatch_count();
+	let last = LAST_DISPATCH.swap(dispatched, Ordering::SeqCst);
+	if context.interrupts_were_enabled() || dispatched != last {
+		STUCK_SAMPLES.store(0, Ordering::SeqCst);
+		return;
+	}
+
+	let stuck = STUCK_SAMPLES.fetch_add(1, Ordering::SeqCst) + 1;
+	if stuck >= WATCHDOG_LIMIT.load(Ordering::SeqCst) {
+		WATCHDOG_ARMED.store(false, Ordering::SeqCst);
+		report::die(format_args!("WATCHDOG! hard lockup, interrupts off \
+									for {}ms", stuck * 1000 / WATCHDOG_HZ),
+					2, context);
+	}
+}
@@ -0,0 +1,164 @@
+// file:	pic.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Driver for the legacy pair of chained 8259 PICs
+
+// includes
+use crate::arch::{Arch, PortIo};
+
+/// vector the master PIC's IRQ 0 gets remapped to, right after the exceptions
+pub const PIC_1_OFFSET: u8 = 32;
+/// vector the slave PIC's IRQ 8 gets remapped to
+pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+
+// the slave PIC is wired into the master's IRQ 2
+const CASCADE_IRQ: u8 = 2;
+
+// initialization command words and operation command words
+const ICW1_INIT: u8 = 0x10;
+const ICW1_ICW4: u8 = 0x01;
+const ICW4_8086: u8 = 0x01;
+const OCW2_EOI: u8 = 0x20;
+const OCW3_READ_ISR: u8 = 0x0b;
+
+// writes to port 0x80 take long enough for the PICs to keep up on real
+// hardware, which needs a little time between command words
+const WAIT_PORT: u16 = 0x80;
+
+// Pic is a single 8259, which handles 8 IRQ lines
+struct Pic {
+	offset: u8,
+	command: u16,
+	data: u16,
+}
+
+impl Pic {
+	/// end_of_interrupt() tells this PIC we're done with its current IRQ
+	unsafe fn end_of_interrupt(&mut self) {
+		Arch::write_port(self.command, OCW2_EOI);
+	}
+
+	/// in_service() reads the in-service register, which has a bit set for
+	/// every IRQ the PIC thinks the cpu is currently handling
+	unsafe fn in_service(&mut self) -> u8 {
+		Arch::write_port(self.command, OCW3_READ_ISR);
+		Arch::read_port(self.command)
+	}
+}
+
+// ChainedPics is the master/slave pair found on every pc
+pub struct ChainedPics {
+	pics: [Pic; 2],
+}
+
+impl ChainedPics {
+	/// new() is the constructor for ChainedPics. it doesn't touch the
+	/// hardware, so it's fine to call it in a static initializer.
+	pub const fn new(offset_1: u8, offset_2: u8) -> ChainedPics {
+		ChainedPics {
+			pics: [
+				Pic {
+					offset: offset_1,
+					command: 0x20,
+					data: 0x21,
+				},
+				Pic {
+					offset: offset_2,
+					command: 0xa0,
+					data: 0xa1,
+				},
+			],
+		}
+	}
+
+	/// initialize() remaps both PICs to their offsets and masks every line
+	/// except the cascade, so nothing fires until a driver asks for it
+	pub unsafe fn initialize(&mut self) {
+		let (master, slave) = (&self.pics[0], &self.pics[1]);
+		let io_wait = || Arch::write_port(WAIT_PORT, 0);
+
+		// ICW1: start the init sequence, and tell them we'll send an ICW4
+		Arch::write_port(master.command, ICW1_INIT | ICW1_ICW4);
+		io_wait();
+		Arch::write_port(slave.command, ICW1_INIT | ICW1_ICW4);
+		io_wait();
+
+		// ICW2: the vector offsets
+		Arch::write_port(master.data, master.offset);
+		io_wait();
+		Arch::write_port(slave.data, slave.offset);
+		io_wait();
+
+		// ICW3: tell the master where the slave is, and the slave its id
+		Arch::write_port(master.data, 1 << CASCADE_IRQ);
+		io_wait();
+		Arch::write_port(slave.data, CASCADE_IRQ);
+		io_wait();
+
+		// ICW4: 8086 mode
+		Arch::write_port(master.data, ICW4_8086);
+		io_wait();
+		Arch::write_port(slave.data, ICW4_8086);
+		io_wait();
+
+		// mask everything but the cascade
+		Arch::write_port(master.data, !(1 << CASCADE_IRQ));
+		Arch::write_port(slave.data, 0xff);
+	}
+
+	/// handles_vector() returns whether a vector belongs to one of our PICs
+	pub fn handles_vector(&self, vector: u8) -> bool {
+		self.pics.iter().any(|pic| {
+			pic.offset <= vector && vector < pic.offset + 8
+		})
+	}
+
+	/// mask() disables an IRQ line, 0-15
+	pub unsafe fn mask(&mut self, irq: u8) {
+		let pic = &self.pics[(irq / 8) as usize];
+		let mask = Arch::read_port(pic.data) | (1 << (irq % 8));
+		Arch::write_port(pic.data, mask);
+	}
+
+	/// unmask() enables an IRQ line, 0-15
+	pub unsafe fn unmask(&mut self, irq: u8) {
+		let pic = &self.pics[(irq / 8) as usize];
+		let mask = Arch::read_port(pic.data) & !(1 << (irq % 8));
+		Arch::write_port(pic.data, mask);
+	}
+
+	/// mask_all() disables every line, for when another controller takes over
+	pub unsafe fn mask_all(&mut self) {
+		Arch::write_port(self.pics[0].data, 0xff);
+		Arch::write_port(self.pics[1].data, 0xff);
+	}
+
+	/// is_spurious() checks whether an IRQ 7 or 15 actually happened. the
+	/// PICs raise those when a line drops before the cpu acknowledges it, in
+	/// which case the in-service bit won't be set.
+	pub unsafe fn is_spurious(&mut self, irq: u8) -> bool {
+		match irq {
+			7 => self.pics[0].in_service() & (1 << 7) == 0,
+			15 => self.pics[1].in_service() & (1 << 7) == 0,
+			_ => false,
+		}
+	}
+
+	/// end_of_interrupt() acknowledges an IRQ. the slave's IRQs came through
+	/// the master's cascade line, so they need to be acknowledged on both.
+	pub unsafe fn end_of_interrupt(&mut self, irq: u8) {
+		if irq >= 8 {
+			self.pics[1].end_of_interrupt();
+		}
+		self.pics[0].end_of_interrupt();
+	}
+
+	/// end_of_spurious_interrupt() cleans up after a spurious IRQ. a spurious
+	/// IRQ 7 needs nothing, but a spurious IRQ 15 still went through the
+	/// master's cascade line, which does need acknowledging.
+	pub unsafe fn end_of_spurious_interrupt(&mut self, irq: u8) {
+		if irq >= 8 {
+			self.pics[0].end_of_interrupt();
+		}
+	}
+}
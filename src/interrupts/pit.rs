@@ -0,0 +1,95 @@
+// file:	pit.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Driver for the 8253/8254 programmable interval timer, which we use
+//			as a tick source on PIC systems and as a stopwatch for calibration
+
+// includes
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::arch::{Arch, PortIo};
+
+/// frequency the PIT's counters count down at, in Hz
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+// the PIT's ports, and the one that gates channel 2
+const CHANNEL_0_PORT: u16 = 0x40;
+const CHANNEL_2_PORT: u16 = 0x42;
+const COMMAND_PORT: u16 = 0x43;
+const CONTROL_PORT: u16 = 0x61;
+
+// command register bits
+const CHANNEL_0: u8 = 0b00 << 6;
+const CHANNEL_2: u8 = 0b10 << 6;
+const ACCESS_LOHI: u8 = 0b11 << 4;
+const MODE_INTERRUPT_ON_TERMINAL: u8 = 0b000 << 1;
+const MODE_SQUARE_WAVE: u8 = 0b011 << 1;
+
+// port 0x61 bits that control and report on channel 2
+const GATE_2: u8 = 1 << 0;
+const SPEAKER: u8 = 1 << 1;
+const OUT_2: u8 = 1 << 5;
+
+// the longest we can wait in one go is 65535 ticks, about 54ms
+const MAX_WAIT_MS: u32 = 50;
+
+// the rate channel 0 was last set to, or 0 if it's stopped. we can't read
+// the divisor back out of the PIT, so this is how it gets put back.
+static CHANNEL_0_HZ: AtomicU32 = AtomicU32::new(0);
+
+/// set_periodic() makes channel 0 fire IRQ 0 at (roughly) the given rate
+pub fn set_periodic(hz: u32) {
+	let divisor = (PIT_FREQUENCY / hz.max(19)).min(0xffff) as u16;
+	unsafe {
+		Arch::write_port(COMMAND_PORT,
+							CHANNEL_0 | ACCESS_LOHI | MODE_SQUARE_WAVE);
+		Arch::write_port(CHANNEL_0_PORT, divisor as u8);
+		Arch::write_port(CHANNEL_0_PORT, (divisor >> 8) as u8);
+	}
+	CHANNEL_0_HZ.store(hz, Ordering::SeqCst);
+}
+
+/// periodic_rate() returns the rate channel 0 was last set to, or 0 if it's
+/// stopped or we never set it
+pub fn periodic_rate() -> u32 {
+	CHANNEL_0_HZ.load(Ordering::SeqCst)
+}
+
+/// stop() stops channel 0 from firing IRQ 0. in interrupt on terminal count
+/// mode, the count doesn't start until it's written, so we just don't.
+pub fn stop() {
+	let command = CHANNEL_0 | ACCESS_LOHI | MODE_INTERRUPT_ON_TERMINAL;
+	unsafe { Arch::write_port(COMMAND_PORT, command) };
+	CHANNEL_0_HZ.store(0, Ordering::SeqCst);
+}
+
+/// wait_ms() busy-waits for the given number of milliseconds, using channel 2
+/// so it doesn't need interrupts or disturb the channel 0 tick
+pub fn wait_ms(ms: u32) {
+	let mut remaining = ms;
+	while remaining > 0 {
+		let chunk = remaining.min(MAX_WAIT_MS);
+		wait_ticks((PIT_FREQUENCY / 1000 * chunk) as u16);
+		remaining -= chunk;
+	}
+}
+
+/// wait_ticks() busy-waits for one channel 2 countdown of the given length
+fn wait_ticks(ticks: u16) {
+	unsafe {
+		// gate channel 2 off while it's being programmed, and keep the
+		// speaker out of it
+		let value = Arch::read_port(CONTROL_PORT) & !(GATE_2 | SPEAKER);
+		Arch::write_port(CONTROL_PORT, value);
+
+		let command = CHANNEL_2 | ACCESS_LOHI | MODE_INTERRUPT_ON_TERMINAL;
+		Arch::write_port(COMMAND_PORT, command);
+		Arch::write_port(CHANNEL_2_PORT, ticks as u8);
+		Arch::write_port(CHANNEL_2_PORT, (ticks >> 8) as u8);
+
+		// raising the gate starts the countdown. OUT 2 goes high at 0
+		Arch::write_port(CONTROL_PORT, value | GATE_2);
+		while Arch::read_port(CONTROL_PORT) & OUT_2 == 0 {}
+
+		Arch::write_port(CONTROL_PORT, value);
+	}
+}
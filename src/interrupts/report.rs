@@ -0,0 +1,215 @@
+// file:	report.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Dumps everything we can find out about the cpu when an exception
+//			turns out to be fatal
+
+// includes
+use core::fmt;
+use crate::arch::{Arch, ContextFrame, Cpu};
+use crate::memory;
+use crate::memory::stack::StackInfo;
+use super::PageFaultErrorCode;
+
+// how many bytes of code to dump before and after rip, and of stack
+const CODE_BEFORE: u64 = 16;
+const CODE_AFTER: u64 = 32;
+const STACK_BYTES: u64 = 64;
+
+/// report! prints to both VGA and serial, since the screen is useless when
+/// running headless and serial is useless when somebody's watching the screen.
+/// it goes through the fatal path, since the exception might have hit
+/// while somebody was holding one of the output locks.
+macro_rules! report {
+	($($arg:tt)*) => {
+		crate::fatal_println!($($arg)*)
+	}
+}
+
+// the names of the architectural exceptions, by vector
+const EXCEPTION_NAMES: [&str; 32] = [
+	"Divide by Zero",
+	"Debug",
+	"Non-Maskable Interrupt",
+	"Breakpoint",
+	"Overflow",
+	"Bound Range Exceeded",
+	"Invalid Opcode",
+	"Device Not Available",
+	"Double Fault",
+	"Coprocessor Segment Overrun",
+	"Invalid TSS",
+	"Segment Not Present",
+	"Stack-Segment Fault",
+	"General Protection Fault",
+	"Page Fault",
+	"Reserved Exception",
+	"x87 Floating-Point Exception",
+	"Alignment Check",
+	"Machine Check",
+	"SIMD Floating-Point Exception",
+	"Virtualization Exception",
+	"Control Protection Exception",
+	"Reserved Exception",
+	"Reserved Exception",
+	"Reserved Exception",
+	"Reserved Exception",
+	"Reserved Exception",
+	"Reserved Exception",
+	"Hypervisor Injection Exception",
+	"VMM Communication Exception",
+	"Security Exception",
+	"Reserved Exception",
+];
+
+/// exception_name() returns the name of an exception vector, or None if the
+/// vector isn't an exception at all
+pub fn exception_name(vector: u8) -> Option<&'static str> {
+	EXCEPTION_NAMES.get(vector as usize).cloned()
+}
+
+/// fatal() reports everything it can about the cpu state an exception left
+/// behind, runs the fatal hook and then hangs. nothing it does can fault,
+/// so it's safe to call no matter what state the kernel is in.
+pub fn fatal(vector: u8, context: &dyn ContextFrame) -> ! {
+	match exception_name(vector) {
+		Some(name) => die(format_args!("EXCEPTION! {} (vector {})",
+										name, vector), vector, context),
+		None => die(format_args!("EXCEPTION! Unhandled vector {}", vector),
+					vector, context),
+	}
+}
+
+/// stack_overflow() is fatal() for a fault in a stack's guard, which gets
+/// reported as that stack overflowing instead of as a plain fault
+pub fn stack_overflow(vector: u8, stack: &StackInfo,
+						context: &dyn ContextFrame) -> !
+{
+	die(format_args!("EXCEPTION! kernel stack overflow in stack {}\n\
+						stack is {:#x}..{:#x}, its guard starts at {:#x}",
+						stack.name, stack.bottom.as_u64(), stack.top.as_u64(),
+						stack.guard.as_u64()), vector, context)
+}
+
+/// die() is fatal() with a title of the caller's choosing, for things that
+/// aren't exceptions as such but are just as fatal, like a hard lockup
+pub fn die(title: fmt::Arguments, vector: u8, context: &dyn ContextFrame)
+	-> !
+{
+	report!("\n{}", title);
+	report_error_code(vector, context.error_code());
+	report!("{}", RegisterDump(context));
+	report!("{}", SystemRegisterDump);
+
+	let rip = context.instruction_pointer();
+	report!("code around rip:");
+	hexdump(rip.wrapping_sub(CODE_BEFORE), CODE_BEFORE + CODE_AFTER);
+	report!("top of stack:");
+	hexdump(context.stack_pointer(), STACK_BYTES);
+
+	super::run_fatal_hook(vector);
+	Arch::halt_forever()
+}
+
+/// report_error_code() decodes the error code for the exceptions where it
+/// means something more than a number
+fn report_error_code(vector: u8, error_code: u64) {
+	match vector {
+		// #TS, #NP, #SS and #GP push a selector error code
+		10 | 11 | 12 | 13 => {
+			if error_code == 0 {
+				report!("error code: 0 (not caused by a selector)");
+				return;
+			}
+			let table = match (error_code >> 1) & 0b11 {
+				0b00 => "GDT",
+				0b10 => "LDT",
+				_ => "IDT",
+			};
+			report!("error code: {:#x} ({} index {:#x}{})", error_code, table,
+					(error_code >> 3) & 0x1fff,
+					if error_code & 1 != 0 { ", external" } else { "" });
+		},
+		14 => {
+			let flags = PageFaultErrorCode::from_bits_truncate(error_code);
+			report!("error code: {:#x} {:?}", error_code, flags);
+			report!("while accessing {:#x}", read_cr2());
+		},
+		// these push an error code that's always 0 or meaningless to decode
+		8 | 17 | 21 | 29 | 30 => report!("error code: {:#x}", error_code),
+		_ => {},
+	}
+}
+
+// RegisterDump prints every register a context frame saved
+struct RegisterDump<'a>(&'a dyn ContextFrame);
+
+impl<'a> fmt::Display for RegisterDump<'a> {
+	/// fmt() leaves the layout to the frame
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt_registers(f)
+	}
+}
+
+// SystemRegisterDump prints the registers that control the cpu itself
+struct SystemRegisterDump;
+
+impl fmt::Display for SystemRegisterDump {
+	/// fmt() leaves the layout to the arch
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Arch::fmt_system_registers(f)
+	}
+}
+
+/// read_cr2() reads the address the last page fault happened at
+pub(super) fn read_cr2() -> u64 {
+	let cr2: u64;
+	unsafe { asm!("mov $0, cr2" : "=r"(cr2) ::: "intel", "volatile") };
+	cr2
+}
+
+/// hexdump() prints len bytes starting at start, 16 to a line. bytes on
+/// pages that aren't mapped get printed as ?? instead of faulting.
+fn hexdump(start: u64, len: u64) {
+	let start = start & !0xf;
+	let mut line = start;
+	while line < start.wrapping_add(len) {
+		let mut bytes = [None; 16];
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			let addr = line.wrapping_add(i as u64);
+			if memory::is_mapped(addr) {
+				*byte = Some(unsafe { *(addr as *const u8) });
+			}
+		}
+
+		// build the line up front, so VGA and serial get the same thing
+		let mut text = [b' '; 16 * 3 + 16];
+		for (i, byte) in bytes.iter().enumerate() {
+			let (hex, ascii) = match *byte {
+				Some(b) => (hex_digits(b), printable(b)),
+				None => ([b'?', b'?'], b'?'),
+			};
+			text[i * 3] = hex[0];
+			text[i * 3 + 1] = hex[1];
+			text[16 * 3 + i] = ascii;
+		}
+		let text = unsafe { core::str::from_utf8_unchecked(&text) };
+		report!("{:016x}: {}", line, text);
+
+		line = match line.checked_add(16) {
+			Some(next) => next,
+			None => break,
+		};
+	}
+}
+
+/// hex_digits() turns a byte into its two lowercase hex digits
+fn hex_digits(byte: u8) -> [u8; 2] {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+	[DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xf) as usize]]
+}
+
+/// printable() returns a byte if it's printable ascii, and '.' if it isn't
+fn printable(byte: u8) -> u8 {
+	if byte.is_ascii_graphic() || byte == b' ' { byte } else { b'.' }
+}
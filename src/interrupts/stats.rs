@@ -0,0 +1,140 @@
+// file:	stats.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Per-vector interrupt statistics. every entry point bumps its
+//			vector's counters, and dump_interrupts() prints them all in a
+//			table like /proc/interrupts.
+
+// includes
+use core::arch::x86_64::_rdtsc;
+use core::mem;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+use crate::dual_println;
+use super::{apic, irq, report};
+use super::irq::{IRQ_BASE_VECTOR, IRQ_LINES};
+
+// Counters is what we keep for a single vector. it's all atomics, since an
+// NMI or machine check can land in the middle of another vector's update.
+struct Counters {
+	count: AtomicU64,
+	spurious: AtomicU64,
+	unhandled: AtomicU64,
+	last_tsc: AtomicU64,
+}
+
+/// VectorStats is a snapshot of a vector's counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectorStats {
+	// how many times the vector has fired, spurious and unhandled included
+	pub count: u64,
+	// how many of those the controller said weren't real
+	pub spurious: u64,
+	// how many of those nobody had a handler for, or claimed
+	pub unhandled: u64,
+	// the TSC as of the last time it fired, or 0 if it never has
+	pub last_tsc: u64,
+}
+
+lazy_static! {
+	// zeroed atomics are just atomics holding 0, and this way we don't have
+	// to write out 256 of them. init() makes sure it's set up before anything
+	// can fire, since an NMI spinning on it mid-setup would never get out.
+	static ref COUNTERS: [Counters; 256] = unsafe { mem::zeroed() };
+}
+
+/// init() sets up the counters. it has to run before the IDT is loaded, so
+/// no handler is ever the first to touch them.
+pub(super) fn init() {
+	lazy_static::initialize(&COUNTERS);
+}
+
+/// record() counts a vector firing. every entry point calls this first
+/// thing, so it's kept to an increment and a timestamp.
+pub(super) fn record(vector: u8) {
+	let counters = &COUNTERS[vector as usize];
+	counters.count.fetch_add(1, Ordering::Relaxed);
+	counters.last_tsc.store(unsafe { _rdtsc() }, Ordering::Relaxed);
+}
+
+/// record_spurious() marks the vector's last firing as spurious
+pub(super) fn record_spurious(vector: u8) {
+	COUNTERS[vector as usize].spurious.fetch_add(1, Ordering::Relaxed);
+}
+
+/// record_unhandled() marks the vector's last firing as unhandled
+pub(super) fn record_unhandled(vector: u8) {
+	COUNTERS[vector as usize].unhandled.fetch_add(1, Ordering::Relaxed);
+}
+
+/// vector_stats() returns a snapshot of a vector's counters
+pub fn vector_stats(vector: u8) -> VectorStats {
+	let counters = &COUNTERS[vector as usize];
+	VectorStats {
+		count: counters.count.load(Ordering::Relaxed),
+		spurious: counters.spurious.load(Ordering::Relaxed),
+		unhandled: counters.unhandled.load(Ordering::Relaxed),
+		last_tsc: counters.last_tsc.load(Ordering::Relaxed),
+	}
+}
+
+/// irq_line() returns the IRQ line a vector belongs to, if it's one of theirs
+fn irq_line(vector: u8) -> Option<u8> {
+	if vector >= IRQ_BASE_VECTOR && vector < IRQ_BASE_VECTOR + IRQ_LINES {
+		Some(vector - IRQ_BASE_VECTOR)
+	} else {
+		None
+	}
+}
+
+/// vector_name() describes what a vector that isn't an IRQ line is used for
+fn vector_name(vector: u8) -> &'static str {
+	if let Some(name) = report::exception_name(vector) {
+		return name;
+	}
+	match vector {
+		apic::TIMER_VECTOR => "APIC timer",
+		apic::ERROR_VECTOR => "APIC error",
+		apic::SPURIOUS_VECTOR => "APIC spurious",
+		crate::syscall::SYSCALL_VECTOR => "system call",
+		_ => "unexpected",
+	}
+}
+
+/// dump_interrupts() prints every vector that's ever fired, with its
+/// counters, then the totals. IRQ lines get their line number and the
+/// controller they're coming through.
+pub fn dump_interrupts() {
+	let now = unsafe { _rdtsc() };
+	let controller = irq::controller();
+	dual_println!("vec      count   spurious  unhandled  cycles ago  source");
+
+	let mut total = VectorStats {
+		count: 0,
+		spurious: 0,
+		unhandled: 0,
+		last_tsc: 0,
+	};
+	for vector in 0..=255u8 {
+		let stats = vector_stats(vector);
+		if stats.count == 0 {
+			continue;
+		}
+		total.count += stats.count;
+		total.spurious += stats.spurious;
+		total.unhandled += stats.unhandled;
+
+		let ago = now.wrapping_sub(stats.last_tsc);
+		if let Some(line) = irq_line(vector) {
+			dual_println!("{:#04x} {:>10} {:>10} {:>10} {:>11}  IRQ {} \
+							({:?})", vector, stats.count, stats.spurious,
+							stats.unhandled, ago, line, controller);
+		} else {
+			dual_println!("{:#04x} {:>10} {:>10} {:>10} {:>11}  {}",
+							vector, stats.count, stats.spurious,
+							stats.unhandled, ago, vector_name(vector));
+		}
+	}
+	dual_println!("all  {:>10} {:>10} {:>10}", total.count, total.spurious,
+					total.unhandled);
+}
@@ -4,37 +4,198 @@
 // desc:	x86-arch interrupt handler implementation
 
 // includes
-use crate::println;
+use crate::{gdt, println, syscall};
 use lazy_static::lazy_static;
+use super::{apic, stats};
 
 // struct to represent the exception stack frame
 #[derive(Debug)]
 #[repr(C)]
-struct ExceptionStackFrame {
-	instruction_pointer: u64,
-	code_segment: u64,
-	cpu_flags: u64,
-	stack_pointer: u64,
-	stack_segment: u64,
-}
-
-/// handler! wraps the main handler in a function and grabs the stack frame.
-/// we denote it as a naked function to prevent a prologue from being added and
-/// fucking up the inline assembly from grabbing the stack frame
-macro_rules! handler {
+pub struct ExceptionStackFrame {
+	pub instruction_pointer: u64,
+	pub code_segment: u64,
+	pub cpu_flags: u64,
+	pub stack_pointer: u64,
+	pub stack_segment: u64,
+}
+
+// struct to represent the caller-saved registers, in the order that
+// interrupt_handler! leaves them on the stack
+#[derive(Debug)]
+#[repr(C)]
+pub struct ScratchRegisters {
+	pub r11: u64,
+	pub r10: u64,
+	pub r9: u64,
+	pub r8: u64,
+	pub rdi: u64,
+	pub rsi: u64,
+	pub rdx: u64,
+	pub rcx: u64,
+	pub rax: u64,
+}
+
+// struct to represent every general purpose register, in the order that
+// context_handler! leaves them on the stack
+#[derive(Debug)]
+#[repr(C)]
+pub struct Registers {
+	pub r15: u64,
+	pub r14: u64,
+	pub r13: u64,
+	pub r12: u64,
+	pub rbp: u64,
+	pub rbx: u64,
+	pub scratch: ScratchRegisters,
+}
+
+// struct to represent everything context_handler! hands to its handler. any
+// changes the handler makes to it get loaded back into the cpu on iretq.
+#[derive(Debug)]
+#[repr(C)]
+pub struct InterruptContext {
+	pub registers: Registers,
+	// 0 for exceptions that don't push an error code
+	pub error_code: u64,
+	pub stack_frame: ExceptionStackFrame,
+}
+
+/// push_scratch! pushes every caller-saved register, in the order that
+/// ScratchRegisters expects. it's a macro instead of a const &str because
+/// asm! only takes string literals.
+macro_rules! push_scratch {
+	() => {
+		"push rax
+		push rcx
+		push rdx
+		push rsi
+		push rdi
+		push r8
+		push r9
+		push r10
+		push r11"
+	}
+}
+
+/// pop_scratch! undoes push_scratch!
+macro_rules! pop_scratch {
+	() => {
+		"pop r11
+		pop r10
+		pop r9
+		pop r8
+		pop rdi
+		pop rsi
+		pop rdx
+		pop rcx
+		pop rax"
+	}
+}
+
+/// push_preserved! pushes the callee-saved registers on top of the scratch
+/// ones, in the order that Registers expects.
+macro_rules! push_preserved {
+	() => {
+		"push rbx
+		push rbp
+		push r12
+		push r13
+		push r14
+		push r15"
+	}
+}
+
+/// pop_preserved! undoes push_preserved!
+macro_rules! pop_preserved {
+	() => {
+		"pop r15
+		pop r14
+		pop r13
+		pop r12
+		pop rbp
+		pop rbx"
+	}
+}
+
+/// interrupt_handler! wraps a handler that returns. it saves the caller-saved
+/// registers (the handler saves the rest itself, because it's extern "C"),
+/// hands the handler a mutable stack frame and then iretq's back to wherever
+/// the stack frame says to go.
+/// stack alignment: the cpu pushes 5 qwords and we push 9, which leaves rsp on
+/// a 16-byte bound for the call.
+macro_rules! interrupt_handler {
 	($name: ident) => {{
 		#[naked]
 		extern "C" fn wrapper() -> ! {
 			unsafe {
-				// asm. load the stack pointer into rdi, then call the handler
-				asm!("mov rdi, rsp
+				asm!(concat!(push_scratch!(), "
+						lea rdi, [rsp + 9*8] // ptr to the stack frame
+						cld // the sysv abi wants the direction flag clear
+						call $0
+						", pop_scratch!(), "
+						iretq")
+						:: "i"($name as extern "C" fn(
+							&mut ExceptionStackFrame))
+						:: "intel", "volatile");
+				::core::intrinsics::unreachable();
+			}
+		}
+		wrapper
+	}}
+}
+
+/// interrupt_handler_with_error_code! does the same thing as
+/// interrupt_handler!, but for exceptions that push an error code. the error
+/// code gets popped off before the iretq, since the cpu won't do it for us.
+/// the extra qword from the error code means we need to align rsp ourselves.
+macro_rules! interrupt_handler_with_error_code {
+	($name: ident) => {{
+		#[naked]
+		extern "C" fn wrapper() -> ! {
+			unsafe {
+				asm!(concat!(push_scratch!(), "
+						mov rsi, [rsp + 9*8] // the error code
+						lea rdi, [rsp + 10*8] // ptr to the stack frame
+						sub rsp, 8 // align the stack pointer to a 16-byte bound
+						cld
+						call $0
+						add rsp, 8
+						", pop_scratch!(), "
+						add rsp, 8 // drop the error code
+						iretq")
+						:: "i"($name as extern "C" fn(
+							&mut ExceptionStackFrame, u64))
+						:: "intel", "volatile");
+				::core::intrinsics::unreachable();
+			}
+		}
+		wrapper
+	}}
+}
+
+/// context_handler! is interrupt_handler!, but it saves every general purpose
+/// register and hands the handler the whole InterruptContext, so the handler
+/// can look at or change anything the interrupted code had in registers.
+/// a fake error code of 0 gets pushed so the layout is always the same.
+macro_rules! context_handler {
+	($name: ident) => {{
+		#[naked]
+		extern "C" fn wrapper() -> ! {
+			unsafe {
+				asm!(concat!("push 0 // fake error code
+						", push_scratch!(), "
+						", push_preserved!(), "
+						mov rdi, rsp // ptr to the InterruptContext
 						sub rsp, 8 // align the stack pointer to a 16-byte bound
-						call $0"
-						:: "i"($name as extern "C" fn(_) -> !)
-						: "rdi" : "intel");
-				// tell the rust compiler that this, in fact, an unreachable
-				// bit of code. otherwise, the compiler would be too stupid 
-				// to read the inline assembly, and know that it diverges
+						cld
+						call $0
+						add rsp, 8
+						", pop_preserved!(), "
+						", pop_scratch!(), "
+						add rsp, 8 // drop the error code
+						iretq")
+						:: "i"($name as extern "C" fn(&mut InterruptContext))
+						:: "intel", "volatile");
 				::core::intrinsics::unreachable();
 			}
 		}
@@ -42,24 +203,58 @@ macro_rules! handler {
 	}}
 }
 
-/// handler_with_error_code! does the same thing as handler, but is adjusted
-/// to take an additional error code argument
-macro_rules! handler_with_error_code {
+/// context_handler_with_error_code! is context_handler! for exceptions where
+/// the cpu already pushed an error code.
+macro_rules! context_handler_with_error_code {
 	($name: ident) => {{
 		#[naked]
 		extern "C" fn wrapper() -> ! {
 			unsafe {
-				// asm. load the stack pointer into rdi, then call the handler
-				asm!("pop rsi // pop the error code into rsi
-						mov rdi, rsp
+				asm!(concat!(push_scratch!(), "
+						", push_preserved!(), "
+						mov rdi, rsp // ptr to the InterruptContext
+						sub rsp, 8 // align the stack pointer to a 16-byte bound
+						cld
+						call $0
+						add rsp, 8
+						", pop_preserved!(), "
+						", pop_scratch!(), "
+						add rsp, 8 // drop the error code
+						iretq")
+						:: "i"($name as extern "C" fn(&mut InterruptContext))
+						:: "intel", "volatile");
+				::core::intrinsics::unreachable();
+			}
+		}
+		wrapper
+	}}
+}
+
+/// vector_context_handler! is context_handler!, but it also passes the vector
+/// number the wrapper is installed at, so one handler can serve many vectors.
+/// $vector has to be a constant, since it gets baked into the wrapper's asm
+macro_rules! vector_context_handler {
+	($name: ident, $vector: expr) => {{
+		#[naked]
+		extern "C" fn wrapper() -> ! {
+			unsafe {
+				asm!(concat!("push 0 // fake error code
+						", push_scratch!(), "
+						", push_preserved!(), "
+						mov rdi, rsp // ptr to the InterruptContext
+						mov rsi, $1
 						sub rsp, 8 // align the stack pointer to a 16-byte bound
-						call $0"
+						cld
+						call $0
+						add rsp, 8
+						", pop_preserved!(), "
+						", pop_scratch!(), "
+						add rsp, 8 // drop the error code
+						iretq")
 						:: "i"($name as extern "C" fn(
-							&ExceptionStackFrame, u64) -> !)
-						: "rdi","rsi" : "intel");
-				// tell the rust compiler that this, in fact, an unreachable
-				// bit of code. otherwise, the compiler would be too stupid 
-				// to read the inline assembly, and know that it diverges
+							&mut InterruptContext, u64)),
+						"i"($vector)
+						:: "intel", "volatile");
 				::core::intrinsics::unreachable();
 			}
 		}
@@ -67,52 +262,313 @@ macro_rules! handler_with_error_code {
 	}}
 }
 
-/// divide_by_zero_handler() is exactly what you think it is.
-extern "C" fn divide_by_zero_handler(stack_frame: &ExceptionStackFrame) -> ! {
-	// print out the stack frame with this
-	println!("\nEXCEPTION! Divide by Zero\n{:#?}", &*stack_frame);
-	loop {}
+/// set_vector_row! points the 16 vectors $row0 through $rowf at $handler,
+/// wrapped with $wrapper (one of the vector_* wrappers). each vector gets its
+/// own wrapper, since the vector number isn't pushed by the cpu and has to be
+/// baked into the wrapper instead.
+macro_rules! set_vector_row {
+	($idt: ident, $row: expr, $wrapper: ident, $handler: ident) => {
+		set_vector!($idt, $row, 0x0, $wrapper, $handler);
+		set_vector!($idt, $row, 0x1, $wrapper, $handler);
+		set_vector!($idt, $row, 0x2, $wrapper, $handler);
+		set_vector!($idt, $row, 0x3, $wrapper, $handler);
+		set_vector!($idt, $row, 0x4, $wrapper, $handler);
+		set_vector!($idt, $row, 0x5, $wrapper, $handler);
+		set_vector!($idt, $row, 0x6, $wrapper, $handler);
+		set_vector!($idt, $row, 0x7, $wrapper, $handler);
+		set_vector!($idt, $row, 0x8, $wrapper, $handler);
+		set_vector!($idt, $row, 0x9, $wrapper, $handler);
+		set_vector!($idt, $row, 0xa, $wrapper, $handler);
+		set_vector!($idt, $row, 0xb, $wrapper, $handler);
+		set_vector!($idt, $row, 0xc, $wrapper, $handler);
+		set_vector!($idt, $row, 0xd, $wrapper, $handler);
+		set_vector!($idt, $row, 0xe, $wrapper, $handler);
+		set_vector!($idt, $row, 0xf, $wrapper, $handler);
+	}
+}
+
+/// set_vector! points a single vector at $handler, wrapped with $wrapper
+macro_rules! set_vector {
+	($idt: ident, $row: expr, $col: expr, $wrapper: ident, $handler: ident)
+		=> {{
+		// this has to be a const so the wrapper's asm sees a literal
+		const VECTOR: u64 = $row * 0x10 + $col;
+		$idt.set_handler(VECTOR as u8, $wrapper!($handler, VECTOR))
+	}}
+}
+
+/// fatal_handler! defines a handler for an exception we can't do anything
+/// about besides dumping the cpu state and hanging. the same handler works
+/// with both context_handler! and context_handler_with_error_code!.
+macro_rules! fatal_handler {
+	($name: ident, $vector: expr) => {
+		extern "C" fn $name(context: &mut InterruptContext) {
+			stats::record($vector);
+			super::report::fatal($vector, context);
+		}
+	}
+}
+
+// the architectural exceptions we can't recover from yet
+fatal_handler!(divide_by_zero_handler, 0);
+fatal_handler!(bound_range_handler, 5);
+fatal_handler!(invalid_opcode_handler, 6);
+fatal_handler!(coprocessor_segment_overrun_handler, 9);
+fatal_handler!(invalid_tss_handler, 10);
+fatal_handler!(segment_not_present_handler, 11);
+fatal_handler!(stack_segment_fault_handler, 12);
+fatal_handler!(general_protection_fault_handler, 13);
+fatal_handler!(x87_floating_point_handler, 16);
+fatal_handler!(alignment_check_handler, 17);
+fatal_handler!(simd_floating_point_handler, 19);
+fatal_handler!(virtualization_handler, 20);
+fatal_handler!(control_protection_handler, 21);
+fatal_handler!(vmm_communication_handler, 29);
+fatal_handler!(security_exception_handler, 30);
+
+/// double_fault_handler() runs on its own IST stack, so that it still works
+/// when the fault came from overflowing the kernel stack. that's usually how
+/// an overflow ends up here, since the cpu can't push the page fault's frame
+/// onto the stack that just ran out, so cr2 gets checked against the guards.
+/// a double fault is an abort, so there's never anything to return to.
+extern "C" fn double_fault_handler(context: &mut InterruptContext) {
+	use crate::memory::stack;
+	use x86_64::VirtAddr;
+
+	stats::record(8);
+	let address = VirtAddr::new(super::report::read_cr2());
+	if let Some(stack) = stack::overflowed_stack(address) {
+		super::report::stack_overflow(8, &stack, context);
+	}
+	super::report::fatal(8, context);
+}
+
+/// page_fault_handler() reports faults in a stack's guard as overflowing
+/// that stack. anything else, it gives whoever owns the faulting address a
+/// chance to fix, and resumes the faulting instruction if they do.
+extern "C" fn page_fault_handler(context: &mut InterruptContext) {
+	use crate::memory::fault::{self, PageFault};
+	use crate::memory::stack;
+	use x86_64::VirtAddr;
+
+	stats::record(14);
+	// cr2 is always canonical here, since non-canonical accesses #GP instead
+	let page_fault = PageFault {
+		address: VirtAddr::new(super::report::read_cr2()),
+		error: PageFaultErrorCode::from_bits_truncate(context.error_code),
+	};
+	if let Some(stack) = stack::overflowed_stack(page_fault.address) {
+		stats::record_unhandled(14);
+		super::report::stack_overflow(14, &stack, context);
+	}
+	if !fault::resolve(&page_fault, context) {
+		stats::record_unhandled(14);
+		super::report::fatal(14, context);
+	}
+}
+
+/// device_not_available_handler() handles #NM, which is how lazy FPU
+/// switching finds out a context has started using the FPU
+extern "C" fn device_not_available_handler(context: &mut InterruptContext) {
+	stats::record(7);
+	if !crate::fpu::handle_device_not_available() {
+		stats::record_unhandled(7);
+		super::report::fatal(7, context);
+	}
+}
+
+/// nmi_handler() runs on its own IST stack, since an NMI can land anywhere,
+/// even on the first instruction of a syscall before it's switched stacks
+extern "C" fn nmi_handler(context: &mut InterruptContext) {
+	stats::record(2);
+	super::nmi::handle(context);
 }
 
-/// invalid_opcode_handler() is exactly what you think it is.
-extern "C" fn invalid_opcode_handler(stack_frame: &ExceptionStackFrame) -> ! {
-	let stack_frame = &*stack_frame;
-	println!("\nEXCEPTION! Invalid Opcode at {:#x}\n{:#?})",
+/// machine_check_handler() runs on its own IST stack, since the cpu's state
+/// is suspect by definition and that includes the stack it was on
+extern "C" fn machine_check_handler(context: &mut InterruptContext) {
+	stats::record(18);
+	super::mce::handle(context);
+}
+
+/// debug_handler() handles #DB. it reports what DR6 says caused it, then
+/// resumes. instruction breakpoints are faults, so the resume flag gets set
+/// to keep the same breakpoint from firing again as soon as we iretq.
+extern "C" fn debug_handler(stack_frame: &mut ExceptionStackFrame) {
+	use x86_64::registers::rflags::RFlags;
+
+	stats::record(1);
+	let dr6: u64;
+	unsafe {
+		asm!("mov $0, dr6" : "=r"(dr6) ::: "intel", "volatile");
+		// DR6 is sticky, so clear it for next time
+		asm!("mov dr6, $0" :: "r"(0u64) :: "intel", "volatile");
+	}
+
+	println!("\nEXCEPTION! Debug at {:#x}, DR6: {:#x}",
+				stack_frame.instruction_pointer, dr6);
+	stack_frame.cpu_flags |= RFlags::RESUME_FLAG.bits();
+}
+
+/// breakpoint_handler() handles int3. #BP is a trap, so the stack frame
+/// already points past the int3 and we can just report and return.
+extern "C" fn breakpoint_handler(stack_frame: &mut ExceptionStackFrame) {
+	stats::record(3);
+	println!("\nEXCEPTION! Breakpoint at {:#x}\n{:#?}",
 				stack_frame.instruction_pointer, stack_frame);
-	loop{}
+}
+
+/// overflow_handler() handles the into instruction. #OF is a trap, so the
+/// stack frame already points past into and we can just report and return.
+extern "C" fn overflow_handler(stack_frame: &mut ExceptionStackFrame) {
+	stats::record(4);
+	println!("\nEXCEPTION! Overflow at {:#x}",
+				stack_frame.instruction_pointer);
+}
+
+/// unhandled_handler() catches every vector that doesn't have a real handler,
+/// so that stray exceptions and interrupts get reported instead of
+/// triple-faulting.
+extern "C" fn unhandled_handler(context: &mut InterruptContext, vector: u64) {
+	stats::record(vector as u8);
+	stats::record_unhandled(vector as u8);
+	super::report::fatal(vector as u8, context);
+}
+
+/// irq_entry() is where every PIC vector lands. it just hands off to the
+/// generic irq dispatch layer.
+extern "C" fn irq_entry(context: &mut InterruptContext, vector: u64) {
+	stats::record(vector as u8);
+	super::irq::dispatch(vector as u8, context);
+}
+
+/// apic_timer_entry() is where the local APIC timer lands
+extern "C" fn apic_timer_entry(context: &mut InterruptContext) {
+	stats::record(apic::TIMER_VECTOR);
+	super::irq::dispatch_timer(context);
+}
+
+/// apic_error_handler() reports local APIC errors
+extern "C" fn apic_error_handler(_stack_frame: &mut ExceptionStackFrame) {
+	stats::record(apic::ERROR_VECTOR);
+	super::irq::apic_error();
+}
+
+/// syscall_entry() is where int 0x80 lands. the result goes back in rax for
+/// the iretq.
+extern "C" fn syscall_entry(context: &mut InterruptContext) {
+	stats::record(syscall::SYSCALL_VECTOR);
+	syscall::handle(context);
+}
+
+/// apic_spurious_handler() counts spurious local APIC interrupts. those
+/// mustn't be acknowledged, so there's nothing else to do.
+extern "C" fn apic_spurious_handler(_stack_frame: &mut ExceptionStackFrame) {
+	stats::record(apic::SPURIOUS_VECTOR);
+	stats::record_spurious(apic::SPURIOUS_VECTOR);
+	super::irq::apic_spurious();
 }
 
 // struct with constants used for translating page fault's error codes
 bitflags! {
-	struct PageFaultErrorCode: u64 {
-		const PROTECTION_VALIDATION=1 << 0;
+	pub struct PageFaultErrorCode: u64 {
+		const PROTECTION_VALIDATION = 1 << 0;
 		const CAUSED_BY_WRITE = 1 << 1;
 		const USER_MODE = 1 << 2;
-		const MALFORMMED_TABLE = 1 << 3;
+		const MALFORMED_TABLE = 1 << 3;
 		const INSTRUCTION_FETCH = 1 << 4;
+		const PROTECTION_KEY = 1 << 5;
+		const SHADOW_STACK = 1 << 6;
+		const SGX = 1 << 15;
 	}
 }
 
-/// page_fault_handler() is exactly what you think it is.
-extern "C" fn page_fault_handler(stack_frame: &ExceptionStackFrame,
-									error_code: u64) -> !
-{
-	use x86_64::registers::control;
-	println!("\nEXCEPTION! Page Fault while accessing {:#x}\
-				\nerror code: {:?}\n{:#?}",
-				unsafe { control::Cr2::read().as_u64() },
-				PageFaultErrorCode::from_bits(error_code).unwrap(),
-				&*stack_frame);
-	loop {}
-}
-
 // create a static instance of Idt to act as the global
 lazy_static! {
 	static ref IDT: idt::Idt = {
 		let mut idt = idt::Idt::new();
-		idt.set_handler(0, handler!(divide_by_zero_handler));
-		idt.set_handler(6, handler!(invalid_opcode_handler));
-		idt.set_handler(14, handler_with_error_code!(page_fault_handler));
+
+		// point every vector at the catch-all handler first, then overwrite
+		// the ones we actually know how to deal with
+		set_vector_row!(idt, 0x0, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x1, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x3, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x4, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x5, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x6, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x7, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x8, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0x9, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xa, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xb, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xc, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xd, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xe, vector_context_handler, unhandled_handler);
+		set_vector_row!(idt, 0xf, vector_context_handler, unhandled_handler);
+
+		// the IRQ lines start at 0x20, and all go to the irq layer. there are
+		// 16 of them on the PIC and 24 on the APIC.
+		set_vector_row!(idt, 0x2, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x0, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x1, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x2, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x3, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x4, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x5, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x6, vector_context_handler, irq_entry);
+		set_vector!(idt, 0x3, 0x7, vector_context_handler, irq_entry);
+
+		// local APIC vectors
+		idt.set_handler(apic::TIMER_VECTOR, context_handler!(apic_timer_entry));
+		idt.set_handler(apic::ERROR_VECTOR,
+			interrupt_handler!(apic_error_handler));
+		idt.set_handler(apic::SPURIOUS_VECTOR,
+			interrupt_handler!(apic_spurious_handler));
+
+		// the system call gate is the only one ring 3 is allowed to int to
+		idt.set_handler(syscall::SYSCALL_VECTOR,
+			context_handler!(syscall_entry))
+			.set_privilege_level(3);
+
+		// architectural exceptions. 15 and 22-28 and 31 are reserved, so
+		// they're left pointing at unhandled_handler()
+		idt.set_handler(0, context_handler!(divide_by_zero_handler));
+		idt.set_handler(1, interrupt_handler!(debug_handler));
+		idt.set_handler(2, context_handler!(nmi_handler))
+			.set_stack_index(gdt::NMI_IST_INDEX);
+		idt.set_handler(3, interrupt_handler!(breakpoint_handler));
+		idt.set_handler(4, interrupt_handler!(overflow_handler));
+		idt.set_handler(5, context_handler!(bound_range_handler));
+		idt.set_handler(6, context_handler!(invalid_opcode_handler));
+		idt.set_handler(7, context_handler!(device_not_available_handler));
+		idt.set_handler(8,
+			context_handler_with_error_code!(double_fault_handler))
+			.set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+		idt.set_handler(9,
+			context_handler!(coprocessor_segment_overrun_handler));
+		idt.set_handler(10,
+			context_handler_with_error_code!(invalid_tss_handler));
+		idt.set_handler(11,
+			context_handler_with_error_code!(segment_not_present_handler));
+		idt.set_handler(12,
+			context_handler_with_error_code!(stack_segment_fault_handler));
+		idt.set_handler(13,
+			context_handler_with_error_code!(general_protection_fault_handler));
+		idt.set_handler(14,
+			context_handler_with_error_code!(page_fault_handler));
+		idt.set_handler(16, context_handler!(x87_floating_point_handler));
+		idt.set_handler(17,
+			context_handler_with_error_code!(alignment_check_handler));
+		idt.set_handler(18, context_handler!(machine_check_handler))
+			.set_stack_index(gdt::MACHINE_CHECK_IST_INDEX);
+		idt.set_handler(19, context_handler!(simd_floating_point_handler));
+		idt.set_handler(20, context_handler!(virtualization_handler));
+		idt.set_handler(21,
+			context_handler_with_error_code!(control_protection_handler));
+		idt.set_handler(29,
+			context_handler_with_error_code!(vmm_communication_handler));
+		idt.set_handler(30,
+			context_handler_with_error_code!(security_exception_handler));
 		idt
 	};
 }
@@ -132,14 +588,14 @@ pub mod idt {
 	// define HandlerFunc as c function that diverges
 	pub type HandlerFunc = extern "C" fn() -> !;
 
-	// struct of Idt, which is an array of 16 entries
-	pub struct Idt([Entry; 16]);
+	// struct of Idt, which is an array of 256 entries, one for every vector
+	pub struct Idt([Entry; 256]);
 
 	// implementation for Idt
 	impl Idt {
 		/// new() is the constructor for Idt
 		pub fn new() -> Idt {
-			Idt([Entry::missing(); 16])
+			Idt([Entry::missing(); 256])
 		}
 
 		/// set_handler() adds new handlers to the idt
@@ -234,21 +690,26 @@ pub mod idt {
 			self
 		}
 
-		/// disable_interrupts() TODO(garnt): label
+		/// disable_interrupts() picks between an interrupt gate, which clears
+		/// IF on the way in, and a trap gate, which leaves it alone
 		pub fn disable_interrupts(&mut self, disable: bool) -> &mut Self {
 			self.0.set_bit(8, !disable);
 			self
 		}
 
-		/// set_privilege_level() TODO(garnt): label
+		/// set_privilege_level() sets the least privileged ring (0-3) that's
+		/// allowed to reach this gate with a software int. hardware
+		/// interrupts and exceptions ignore it.
 		pub fn set_privilege_level(&mut self, dpl: u16) -> &mut Self {
 			self.0.set_bits(13..15, dpl);
 			self
 		}
 
-		/// set_stack_index() TODO(garnt): label
+		/// set_stack_index() makes the cpu switch to the given IST stack
+		/// (0-6, in TSS order) before calling the handler. the field itself
+		/// is 1-based, since 0 there means "don't switch stacks".
 		pub fn set_stack_index(&mut self, index: u16) -> &mut Self {
-			self.0.set_bits(0..3, index);
+			self.0.set_bits(0..3, index + 1);
 			self
 		}
 	}
@@ -13,10 +13,22 @@
 #![feature(asm)]
 // allow naked functions for some true fuckery
 #![feature(naked_functions)]
+// allow the alloc crate, and handling running out of heap ourselves
+#![feature(alloc)]
+#![feature(alloc_error_handler)]
 
 
+pub mod acpi;
+pub mod allocator;
+pub mod arch;
+pub mod cpu;
+pub mod fpu;
+pub mod gdt;
 pub mod interrupts;
+pub mod memory;
 pub mod serial;
+pub mod sync;
+pub mod syscall;
 pub mod vga_buffer;
 
 // import bitflags and bit_field
@@ -25,11 +37,51 @@ pub mod vga_buffer;
 extern crate bitflags;
 extern crate bit_field;
 
+// Box, Vec and friends, backed by the kernel heap
+extern crate alloc;
+
+/// fatal_println! prints to both VGA and serial without waiting on their
+/// locks, breaking the VGA one if somebody's holding it. it's only for
+/// panics and fatal exceptions, where whoever was holding a lock is never
+/// going to release it, and getting the message out matters more than a
+/// garbled line. nothing can run after it but the end.
+#[macro_export]
+macro_rules! fatal_println {
+	($($arg:tt)*) => {{
+		$crate::vga_buffer::_fatal_print(
+			format_args!("{}\n", format_args!($($arg)*)));
+		$crate::serial::_emergency_print(
+			format_args!("{}\n", format_args!($($arg)*)));
+	}};
+}
+
+/// emergency_println! is for handlers that can interrupt anything but go
+/// on to return, like NMIs. it never breaks a lock: serial goes through the
+/// early console, and VGA only gets the message if its lock is free.
+#[macro_export]
+macro_rules! emergency_println {
+	($($arg:tt)*) => {{
+		$crate::vga_buffer::_emergency_print(
+			format_args!("{}\n", format_args!($($arg)*)));
+		$crate::serial::_emergency_print(
+			format_args!("{}\n", format_args!($($arg)*)));
+	}};
+}
+
+/// dual_println! prints to both VGA and serial, so reports like the
+/// interrupt table show up whether or not anyone's watching the screen
+#[macro_export]
+macro_rules! dual_println {
+	($($arg:tt)*) => {{
+		$crate::println!($($arg)*);
+		$crate::serial_println!($($arg)*);
+	}};
+}
+
 // exit_qemu() does exactly what you think it does
 // qemu exposes this oddball debug-exit port if you ask it nicely.
 pub unsafe fn exit_qemu() {
-	use x86_64::instructions::port::Port;
+	use crate::arch::{Arch, Cpu};
 
-	let mut port = Port::<u32>::new(0xf4);
-	port.write(0);
+	Arch::exit_emulator();
 }
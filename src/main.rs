@@ -8,31 +8,65 @@
 // also, allow unused imports during testing
 #![cfg_attr(not(test), no_std)]
 #![cfg_attr(not(test), no_main)]
-#![cfg_attr(test, allow(unused_imports))]
+#![cfg_attr(test, allow(dead_code, unused_imports))]
 
 // includes
 use core::panic::PanicInfo;
-use posos::{exit_qemu,println};
+use core::sync::atomic::{AtomicU64, Ordering};
+use bootloader::bootinfo::BootInfo;
+use bootloader::entry_point;
+use posos::{exit_qemu, fatal_println, println};
+use posos::arch::{Arch, Cpu};
+use posos::interrupts::{InterruptContext, IrqReturn};
 
-// this function is called when rust panics. tells you why and then loops.
+// rate the kernel's tick runs at
+const TICK_HZ: u32 = 100;
+
+// how many ticks there have been since boot
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+// this function is called when rust panics. tells you why and then halts.
+// it uses the fatal path, since we might have panicked while printing.
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-	println!("{}", info);
-	loop {}
+	fatal_println!("{}", info);
+	Arch::halt_forever()
 }
 
-// make a bare metal-friendly _start function. no_mangle muzzles the compiler
-#[cfg(not(test))]
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+// tick() runs TICK_HZ times a second, and does anything that needs doing
+// periodically
+fn tick(_context: &mut InterruptContext) -> IrqReturn {
+	let ticks = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+	// corrected machine checks don't interrupt, so go looking once a second
+	if ticks % TICK_HZ as u64 == 0 {
+		posos::interrupts::poll_machine_checks();
+	}
+	IrqReturn::Handled
+}
+
+// have the bootloader call kernel_main() with the boot info. entry_point!
+// makes the bare metal-friendly _start function, and checks the signature.
+entry_point!(kernel_main);
+
+// kernel_main() is where the kernel starts, once the bootloader's done
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
 	println!("GARBAGE! {}", 420.69);
+	posos::cpu::print_summary();
+	posos::memory::init(boot_info);
 
 	// initialize our interrupts
 	posos::interrupts::init();
+	println!("interrupt controller: {:?}", posos::interrupts::controller());
+	posos::interrupts::start_timer(TICK_HZ, tick)
+		.expect("couldn't start the tick");
+	if let Err(err) = posos::interrupts::start_watchdog(1000) {
+		println!("no lockup watchdog: {:?}", err);
+	}
 
+	posos::interrupts::dump_interrupts();
 	println!("It's all good my dude -cory");
 	unsafe { exit_qemu(); }
 	// Hold state indefinitely
-	loop {}
+	Arch::halt_forever()
 }
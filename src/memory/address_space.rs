@@ -0,0 +1,397 @@
+// file:	address_space.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Address spaces. each one has its own p4, with the kernel's entries
+//			shared into it and its own user mappings in the rest. switching
+//			between them is a CR3 write, tagged with a PCID when the cpu has
+//			them, so the TLB doesn't have to be thrown out every time.
+
+// includes
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use crate::arch::{Arch, Interrupts};
+use crate::cpu::{self, FeatureFlags};
+use crate::sync::IrqSpinlock;
+use super::paging::{self, PagingError};
+use super::{entry_addrs, ENTRY_HUGE, ENTRY_PRESENT, P1_ENTRIES_ADDR,
+			P2_ENTRIES_ADDR, P3_ENTRIES_ADDR, P4_ENTRIES_ADDR};
+
+// the p4 entry the bootloader maps the tables recursively through
+const RECURSIVE_INDEX: usize = 511;
+// the p4 entries the kernel's windows live under: the address space scratch
+// page, stacks, slabs, the heap and mmio. they get their p3s up front, so
+// everything mapped in them later is shared by every address space.
+const KERNEL_WINDOWS: [usize; 5] = [506, 507, 508, 509, 510];
+// where a new address space's p4 gets mapped while it's being filled in.
+// it's the bottom of p4 entry 506, right below the stack window.
+const SCRATCH_PAGE: u64 = 0xffff_fd00_0000_0000;
+
+// the physical address bits of a table entry
+const ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
+// where the PCID is in CR3, and the bit that keeps its TLB entries around
+const CR3_PCID_MASK: u64 = 0xfff;
+const CR3_NO_FLUSH: u64 = 1 << 63;
+// CR4 bit that turns PCIDs on
+const CR4_PCIDE: u64 = 1 << 17;
+// how many PCIDs get handed out. 0 is the kernel's, and spaces made once
+// they've run out share it, at the cost of a flush every switch.
+const PCIDS: usize = 256;
+// a left_at that never matches the flush generation
+const STALE: u64 = !0;
+
+// marks user pages whose frames the address space allocated itself, and so
+// has to free. it's one of the bits the cpu ignores.
+const OWNED: PageTableFlags = PageTableFlags::BIT_9;
+
+/// SpaceError is why changing an address space failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceError {
+	// the page is in the kernel's part of the address space, or the upper
+	// half
+	KernelRange,
+	// mapping or unmapping the page failed
+	Paging(PagingError),
+}
+
+impl From<PagingError> for SpaceError {
+	/// from() wraps the paging error
+	fn from(err: PagingError) -> SpaceError {
+		SpaceError::Paging(err)
+	}
+}
+
+// State is what's shared between every address space
+struct State {
+	// whether init() has run
+	ready: bool,
+	use_pcid: bool,
+	// the p4 the bootloader left us on, which is the kernel's own space
+	kernel_p4: u64,
+	// which p4 entries are the kernel's, and shared
+	kernel_entries: [bool; 512],
+	// which PCIDs are handed out
+	pcid_used: [bool; PCIDS],
+	// the p4 each PCID was last used with, and the flush generation as of
+	// when it stopped being the current one. if both still match, none of
+	// its TLB entries can be stale.
+	owner: [u64; PCIDS],
+	left_at: [u64; PCIDS],
+}
+
+static STATE: IrqSpinlock<State> = IrqSpinlock::new(State {
+	ready: false,
+	use_pcid: false,
+	kernel_p4: 0,
+	kernel_entries: [false; 512],
+	pcid_used: [false; PCIDS],
+	owner: [0; PCIDS],
+	left_at: [STALE; PCIDS],
+});
+
+/// read_cr3() returns CR3
+unsafe fn read_cr3() -> u64 {
+	let cr3: u64;
+	asm!("mov $0, cr3" : "=r"(cr3) ::: "intel", "volatile");
+	cr3
+}
+
+/// write_cr3() sets CR3
+unsafe fn write_cr3(cr3: u64) {
+	asm!("mov cr3, $0" :: "r"(cr3) : "memory" : "intel", "volatile");
+}
+
+/// read_cr4() returns CR4
+unsafe fn read_cr4() -> u64 {
+	let cr4: u64;
+	asm!("mov $0, cr4" : "=r"(cr4) ::: "intel", "volatile");
+	cr4
+}
+
+/// write_cr4() sets CR4
+unsafe fn write_cr4(cr4: u64) {
+	asm!("mov cr4, $0" :: "r"(cr4) :: "intel", "volatile");
+}
+
+/// load() switches to p4 with pcid. the TLB entries tagged with pcid are
+/// kept if they were left there by the same p4, and nothing's been unmapped
+/// since. the caller has to have interrupts off.
+unsafe fn load(state: &mut State, p4: u64, pcid: u16) {
+	if !state.use_pcid {
+		write_cr3(p4);
+		return;
+	}
+
+	let now = paging::flush_generation();
+	let old = (read_cr3() & CR3_PCID_MASK) as usize;
+	state.left_at[old] = now;
+
+	let pcid = pcid as usize;
+	let keep = state.owner[pcid] == p4 && state.left_at[pcid] == now;
+	state.owner[pcid] = p4;
+	write_cr3(p4 | pcid as u64 | if keep { CR3_NO_FLUSH } else { 0 });
+}
+
+/// init() gives the kernel's windows their p3s, remembers which p4 entries
+/// are the kernel's, and turns PCIDs on if the cpu has them. the frame
+/// allocator has to be up.
+pub fn init() {
+	for &index in KERNEL_WINDOWS.iter() {
+		paging::populate_p4(index).expect("couldn't populate a kernel window");
+	}
+
+	let mut state = STATE.lock();
+	let cr3 = unsafe { read_cr3() };
+	state.kernel_p4 = cr3 & ADDR_MASK;
+	let p4 = P4_ENTRIES_ADDR as *const [u64; 512];
+	for (index, kernel) in state.kernel_entries.iter_mut().enumerate() {
+		*kernel = index != RECURSIVE_INDEX
+			&& unsafe { (*p4)[index] } & ENTRY_PRESENT != 0;
+	}
+
+	// PCIDE can only be turned on while the current PCID is 0
+	if cpu::has_feature(FeatureFlags::PCID) && cr3 & CR3_PCID_MASK == 0 {
+		unsafe { write_cr4(read_cr4() | CR4_PCIDE) };
+		state.use_pcid = true;
+	}
+	state.ready = true;
+}
+
+/// pcid_enabled() returns whether address spaces are getting PCIDs
+pub fn pcid_enabled() -> bool {
+	STATE.lock().use_pcid
+}
+
+/// switch_to_kernel() switches back to the kernel's own address space. the
+/// caller can't be holding any references into the old space's user half.
+pub unsafe fn switch_to_kernel() {
+	let mut state = STATE.lock();
+	let kernel_p4 = state.kernel_p4;
+	load(&mut state, kernel_p4, 0);
+}
+
+/// AddressSpace is a p4 of our own. it shares the kernel's entries, and
+/// frees everything else it's got when it's dropped.
+#[derive(Debug)]
+pub struct AddressSpace {
+	p4: PhysFrame,
+	pcid: u16,
+}
+
+impl AddressSpace {
+	/// new() makes an address space with the kernel's p4 entries and nothing
+	/// else. init() has to have run.
+	pub fn new() -> Result<AddressSpace, SpaceError> {
+		let mut state = STATE.lock();
+		assert!(state.ready, "address spaces used before memory::init()");
+
+		// fill the new p4 in through the scratch page, since it isn't live
+		// yet. the scratch page is only ever used with the state locked.
+		let p4 = super::allocate_frame()
+			.ok_or(SpaceError::Paging(PagingError::OutOfFrames))?;
+		let scratch = Page::containing_address(VirtAddr::new(SCRATCH_PAGE));
+		let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+		if let Err(err) = unsafe { paging::map_to(scratch, p4, flags) } {
+			unsafe { super::free_frame(p4) };
+			return Err(err.into());
+		}
+		let table = SCRATCH_PAGE as *mut [u64; 512];
+		let active = P4_ENTRIES_ADDR as *const [u64; 512];
+		let recursive = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+		for index in 0..512 {
+			let entry = if index == RECURSIVE_INDEX {
+				p4.start_address().as_u64() | recursive.bits()
+			} else if state.kernel_entries[index] {
+				unsafe { (*active)[index] }
+			} else {
+				0
+			};
+			unsafe { (*table)[index] = entry };
+		}
+		unsafe { paging::unmap(scratch)? };
+
+		// PCID 0 is the kernel's, and the fallback once they're all taken
+		let pcid = if state.use_pcid {
+			(1..PCIDS).find(|&pcid| !state.pcid_used[pcid]).unwrap_or(0)
+		} else {
+			0
+		};
+		if pcid != 0 {
+			state.pcid_used[pcid] = true;
+		}
+		Ok(AddressSpace { p4, pcid: pcid as u16 })
+	}
+
+	/// p4_frame() returns the frame the space's p4 is in
+	pub fn p4_frame(&self) -> PhysFrame {
+		self.p4
+	}
+
+	/// pcid() returns the PCID the space is tagged with. it's 0 if PCIDs
+	/// are off, or there weren't any left.
+	pub fn pcid(&self) -> u16 {
+		self.pcid
+	}
+
+	/// is_active() returns whether the space is the one in CR3
+	pub fn is_active(&self) -> bool {
+		unsafe { read_cr3() & ADDR_MASK == self.p4.start_address().as_u64() }
+	}
+
+	/// switch_to() makes the space the active one. the caller can't be
+	/// holding any references into the old space's user half.
+	pub unsafe fn switch_to(&self) {
+		load(&mut STATE.lock(), self.p4.start_address().as_u64(), self.pcid);
+	}
+
+	/// with_active() runs f with the space active, then switches back to
+	/// whichever one was. it's how an inactive space's tables get changed,
+	/// since they can only be reached through the recursive mapping.
+	fn with_active<F, R>(&self, f: F) -> R
+		where F: FnOnce() -> R
+	{
+		Arch::without_interrupts(|| {
+			let previous = unsafe { read_cr3() };
+			if self.is_active() {
+				return f();
+			}
+			unsafe { self.switch_to() };
+			let result = f();
+			unsafe {
+				load(&mut STATE.lock(), previous & ADDR_MASK,
+						(previous & CR3_PCID_MASK) as u16);
+			}
+			result
+		})
+	}
+
+	/// check_user() returns an error if page can't be a user page, because
+	/// it's in the upper half or under one of the kernel's p4 entries
+	fn check_user(&self, page: Page) -> Result<(), SpaceError> {
+		let index = (page.start_address().as_u64() >> 39) as usize & 0x1ff;
+		if index >= 256 || STATE.lock().kernel_entries[index] {
+			return Err(SpaceError::KernelRange);
+		}
+		Ok(())
+	}
+
+	/// map_user() maps page to a fresh frame that ring 3 can get at, and
+	/// returns the frame. the space owns the frame, and frees it when the
+	/// page is unmapped or the space is dropped.
+	pub fn map_user(&mut self, page: Page, flags: PageTableFlags)
+		-> Result<PhysFrame, SpaceError>
+	{
+		self.check_user(page)?;
+		self.with_active(|| -> Result<PhysFrame, SpaceError> {
+			let flags = flags | PageTableFlags::USER_ACCESSIBLE | OWNED;
+			let frame = unsafe { paging::map_new(page, flags)? };
+			// the tables map_new() made aren't user accessible yet
+			let addr = page.start_address().as_u64();
+			unsafe { super::set_user_accessible(addr) };
+			Ok(frame)
+		})
+	}
+
+	/// map_user_to() maps page to a frame the caller owns, so ring 3 can get
+	/// at it. the frame is left alone when the page is unmapped. the caller
+	/// has to make sure sharing the frame with ring 3 is safe.
+	pub unsafe fn map_user_to(&mut self, page: Page, frame: PhysFrame,
+								flags: PageTableFlags)
+		-> Result<(), SpaceError>
+	{
+		self.check_user(page)?;
+		self.with_active(|| -> Result<(), SpaceError> {
+			paging::map_to(page, frame,
+							flags | PageTableFlags::USER_ACCESSIBLE)?;
+			super::set_user_accessible(page.start_address().as_u64());
+			Ok(())
+		})
+	}
+
+	/// unmap_user() unmaps a user page, freeing its frame if the space owns
+	/// it
+	pub fn unmap_user(&mut self, page: Page) -> Result<(), SpaceError> {
+		self.check_user(page)?;
+		self.with_active(|| -> Result<(), SpaceError> {
+			// the p1 entry is only there to read if the page's mapped
+			let addr = page.start_address().as_u64();
+			let owned = paging::translate_page(page).is_some()
+				&& unsafe { *(entry_addrs(addr)[3] as *const u64) }
+					& OWNED.bits() != 0;
+			let frame = unsafe { paging::unmap(page)? };
+			if owned {
+				unsafe { super::free_frame(frame) };
+			}
+			Ok(())
+		})
+	}
+
+	/// translate() returns the physical address addr is mapped to in the
+	/// space, if it's mapped
+	pub fn translate(&self, addr: VirtAddr) -> Option<PhysAddr> {
+		self.with_active(|| paging::translate_addr(addr))
+	}
+}
+
+/// free_user_tables() frees every table under the active space's user p4
+/// entries, and the frames it owns in them. the space can't be used again
+/// afterwards, since its p4 still points at the freed tables.
+unsafe fn free_user_tables(kernel_entries: &[bool; 512]) {
+	let present = |entry: u64| entry & ENTRY_PRESENT != 0;
+	let table = |entry: u64| {
+		PhysFrame::containing_address(PhysAddr::new(entry & ADDR_MASK))
+	};
+
+	let p4 = &*(P4_ENTRIES_ADDR as *const [u64; 512]);
+	for i in (0..RECURSIVE_INDEX).filter(|&i| !kernel_entries[i]) {
+		if !present(p4[i]) {
+			continue;
+		}
+		let p3 = &*((P3_ENTRIES_ADDR + ((i as u64) << 12))
+					as *const [u64; 512]);
+		for j in 0..512 {
+			if !present(p3[j]) || p3[j] & ENTRY_HUGE != 0 {
+				continue;
+			}
+			let p2 = &*((P2_ENTRIES_ADDR + ((i as u64) << 21)
+						+ ((j as u64) << 12)) as *const [u64; 512]);
+			for k in 0..512 {
+				if !present(p2[k]) || p2[k] & ENTRY_HUGE != 0 {
+					continue;
+				}
+				let p1 = &*((P1_ENTRIES_ADDR + ((i as u64) << 30)
+							+ ((j as u64) << 21) + ((k as u64) << 12))
+							as *const [u64; 512]);
+				for &entry in p1.iter() {
+					if present(entry) && entry & OWNED.bits() != 0 {
+						super::free_frame(table(entry));
+					}
+				}
+				super::free_frame(table(p2[k]));
+			}
+			super::free_frame(table(p3[j]));
+		}
+		super::free_frame(table(p4[i]));
+	}
+}
+
+impl Drop for AddressSpace {
+	/// drop() frees the space's user tables and frames, its p4, and its
+	/// PCID. if it's the active space, the kernel's gets switched to first.
+	fn drop(&mut self) {
+		if self.is_active() {
+			unsafe { switch_to_kernel() };
+		}
+		let kernel_entries = STATE.lock().kernel_entries;
+		self.with_active(|| unsafe { free_user_tables(&kernel_entries) });
+
+		let mut state = STATE.lock();
+		let pcid = self.pcid as usize;
+		if pcid != 0 {
+			state.pcid_used[pcid] = false;
+		}
+		// the frame might come back as another p4, so make sure nothing
+		// tagged with this PCID survives into it
+		state.owner[pcid] = 0;
+		unsafe { super::free_frame(self.p4) };
+	}
+}
@@ -0,0 +1,112 @@
+// file:	fault.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	The page fault resolution chain. subsystems register a resolver for
+//			the range of addresses they own, and get a shot at fixing any
+//			page fault in it before it's treated as fatal.
+
+// includes
+use x86_64::VirtAddr;
+use crate::arch::ContextFrame;
+use crate::interrupts::PageFaultErrorCode;
+use crate::sync::IrqSpinlock;
+
+/// most resolvers that can be registered at once
+pub const MAX_RESOLVERS: usize = 16;
+
+/// PageFault is everything the cpu tells us about a page fault
+#[derive(Debug, Clone, Copy)]
+pub struct PageFault {
+	// the address that was being accessed
+	pub address: VirtAddr,
+	pub error: PageFaultErrorCode,
+}
+
+/// Resolution is what a resolver says about a fault it was shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+	// the fault's fixed, so the faulting instruction can run again
+	Resolved,
+	// the fault's still there, so let the next resolver have a go
+	NotResolved,
+}
+
+/// FaultResolver gets shown faults in its range. it can change the context,
+/// to skip the instruction or fake up a result, as well as fix the mapping.
+/// it mustn't call paging::map_*() or anything else that takes the page
+/// table lock, though: the fault might have been taken while that lock was
+/// held, and it would deadlock waiting on itself.
+pub type FaultResolver = fn(&PageFault, &mut dyn ContextFrame) -> Resolution;
+
+/// FaultError is why registering or unregistering a resolver failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultError {
+	// the range ends before it starts
+	EmptyRange,
+	// there are already MAX_RESOLVERS resolvers
+	TableFull,
+	// the resolver isn't registered on that range
+	NotRegistered,
+}
+
+// Range is a resolver and the addresses it wants to see faults for, from
+// start up to but not including end
+#[derive(Clone, Copy)]
+struct Range {
+	start: VirtAddr,
+	end: VirtAddr,
+	resolver: FaultResolver,
+}
+
+// every registered resolver, in the order they were registered
+static RESOLVERS: IrqSpinlock<[Option<Range>; MAX_RESOLVERS]> =
+	IrqSpinlock::new([None; MAX_RESOLVERS]);
+
+/// register_resolver() adds a resolver for faults in start..end. ranges can
+/// overlap, in which case resolvers get asked in the order they registered.
+pub fn register_resolver(start: VirtAddr, end: VirtAddr,
+							resolver: FaultResolver) -> Result<(), FaultError>
+{
+	if end <= start {
+		return Err(FaultError::EmptyRange);
+	}
+	let mut resolvers = RESOLVERS.lock();
+	let slot = resolvers.iter_mut().find(|slot| slot.is_none())
+		.ok_or(FaultError::TableFull)?;
+	*slot = Some(Range { start, end, resolver });
+	Ok(())
+}
+
+/// unregister_resolver() removes a resolver that was registered at start
+pub fn unregister_resolver(start: VirtAddr, resolver: FaultResolver)
+	-> Result<(), FaultError>
+{
+	let mut resolvers = RESOLVERS.lock();
+	// fn pointers compare by address, which is what we want here
+	let slot = resolvers.iter_mut()
+		.find(|slot| match slot {
+			Some(range) => range.start == start
+				&& range.resolver as usize == resolver as usize,
+			None => false,
+		})
+		.ok_or(FaultError::NotRegistered)?;
+	*slot = None;
+	Ok(())
+}
+
+/// resolve() shows a fault to every resolver whose range it's in, until one
+/// of them fixes it. returns whether one did. called from the page fault
+/// handler, with interrupts off.
+pub fn resolve(fault: &PageFault, context: &mut dyn ContextFrame) -> bool {
+	// if the fault came from inside the lock, there's nobody to ask.
+	// otherwise, copy the table out so resolvers can register more.
+	let resolvers = match RESOLVERS.try_lock() {
+		Some(resolvers) => *resolvers,
+		None => return false,
+	};
+
+	resolvers.iter().filter_map(|range| *range)
+		.filter(|range| range.start <= fault.address
+						&& fault.address < range.end)
+		.any(|range| (range.resolver)(fault, context) == Resolution::Resolved)
+}
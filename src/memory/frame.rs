@@ -0,0 +1,205 @@
+// file:	frame.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	The physical frame allocator. it keeps a bitmap with a bit for every
+//			4KiB frame below the top of usable memory, built from the memory
+//			map the bootloader hands us.
+
+// includes
+use core::slice;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use x86_64::PhysAddr;
+use x86_64::structures::paging::{FrameAllocator, PhysFrame, Size4KiB};
+use crate::sync::IrqSpinlock;
+use super::mmio::map_physical;
+
+/// size of a physical frame
+pub const FRAME_SIZE: u64 = 4096;
+
+// how many frames each bitmap word covers
+const FRAMES_PER_WORD: u64 = 64;
+
+/// FrameStats is how much physical memory there is, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+	// all of the RAM the memory map lists, whoever it belongs to
+	pub total: u64,
+	// the RAM that was free for us to use at boot
+	pub usable: u64,
+	// the RAM that's still free now
+	pub free: u64,
+}
+
+// Bitmap has a bit per frame, set if the frame is in use. frames the memory
+// map didn't say were usable start out set, and stay that way.
+struct Bitmap {
+	words: &'static mut [u64],
+	// how many frames the bitmap covers
+	frames: u64,
+	// how many of them are clear
+	free: u64,
+	// the first word that might have a clear bit in it
+	hint: usize,
+}
+
+impl Bitmap {
+	/// is_used() returns whether a frame is in use
+	fn is_used(&self, frame: u64) -> bool {
+		self.words[(frame / FRAMES_PER_WORD) as usize]
+			& (1 << (frame % FRAMES_PER_WORD)) != 0
+	}
+
+	/// set_used() marks a frame as in use
+	fn set_used(&mut self, frame: u64) {
+		if !self.is_used(frame) {
+			self.words[(frame / FRAMES_PER_WORD) as usize]
+				|= 1 << (frame % FRAMES_PER_WORD);
+			self.free -= 1;
+		}
+	}
+
+	/// set_free() marks a frame as free
+	fn set_free(&mut self, frame: u64) {
+		if self.is_used(frame) {
+			let word = (frame / FRAMES_PER_WORD) as usize;
+			self.words[word] &= !(1 << (frame % FRAMES_PER_WORD));
+			self.free += 1;
+			self.hint = self.hint.min(word);
+		}
+	}
+
+	/// allocate() finds the lowest free frame and marks it as in use
+	fn allocate(&mut self) -> Option<u64> {
+		for word in self.hint..self.words.len() {
+			if self.words[word] == !0 {
+				continue;
+			}
+			self.hint = word;
+			let frame = word as u64 * FRAMES_PER_WORD
+				+ (!self.words[word]).trailing_zeros() as u64;
+			if frame >= self.frames {
+				break;
+			}
+			self.set_used(frame);
+			return Some(frame);
+		}
+		self.hint = self.words.len();
+		None
+	}
+}
+
+// the allocator, once init() has built it. it's an IrqSpinlock, since page
+// fault resolvers need frames too.
+static FRAMES: IrqSpinlock<Option<Bitmap>> = IrqSpinlock::new(None);
+
+// what init() found in the memory map
+static STATS: IrqSpinlock<FrameStats> = IrqSpinlock::new(FrameStats {
+	total: 0,
+	usable: 0,
+	free: 0,
+});
+
+/// is_ram() returns whether a region is RAM at all, whether or not we get
+/// to use it
+fn is_ram(region_type: MemoryRegionType) -> bool {
+	match region_type {
+		MemoryRegionType::Reserved | MemoryRegionType::BadMemory
+			| MemoryRegionType::Empty => false,
+		_ => true,
+	}
+}
+
+/// init() builds the bitmap out of the memory map. the bitmap itself gets
+/// carved out of the first usable region with room for it. everything the
+/// map doesn't call usable, which includes the kernel, the bootloader, the
+/// page tables and the boot info, starts out in use.
+pub unsafe fn init(memory_map: &MemoryMap) {
+	let mut stats = FrameStats { total: 0, usable: 0, free: 0 };
+	let mut top = 0;
+	for region in memory_map.iter() {
+		let size = region.range.end_addr() - region.range.start_addr();
+		if is_ram(region.region_type) {
+			stats.total += size;
+		}
+		if region.region_type == MemoryRegionType::Usable {
+			stats.usable += size;
+			top = top.max(region.range.end_frame_number);
+		}
+	}
+
+	// find somewhere to put the bitmap, and map it
+	let words = ((top + FRAMES_PER_WORD - 1) / FRAMES_PER_WORD) as usize;
+	let bitmap_frames = (words as u64 * 8 + FRAME_SIZE - 1) / FRAME_SIZE;
+	let home = memory_map.iter()
+		.find(|region| region.region_type == MemoryRegionType::Usable
+				&& region.range.end_frame_number
+					- region.range.start_frame_number >= bitmap_frames)
+		.expect("no usable region big enough for the frame bitmap")
+		.range.start_frame_number;
+	let address = map_physical(PhysAddr::new(home * FRAME_SIZE),
+								bitmap_frames * FRAME_SIZE);
+	let mut bitmap = Bitmap {
+		words: slice::from_raw_parts_mut(address.as_mut_ptr(), words),
+		frames: top,
+		free: 0,
+		hint: 0,
+	};
+	for word in bitmap.words.iter_mut() {
+		*word = !0;
+	}
+
+	for region in memory_map.iter() {
+		if region.region_type == MemoryRegionType::Usable {
+			for frame in region.range.start_frame_number
+				..region.range.end_frame_number
+			{
+				bitmap.set_free(frame);
+			}
+		}
+	}
+	for frame in home..home + bitmap_frames {
+		bitmap.set_used(frame);
+	}
+
+	stats.free = bitmap.free * FRAME_SIZE;
+	*STATS.lock() = stats;
+	*FRAMES.lock() = Some(bitmap);
+}
+
+/// allocate_frame() hands out a free frame, or None if there aren't any
+/// left, or init() hasn't run yet
+pub fn allocate_frame() -> Option<PhysFrame> {
+	let frame = FRAMES.lock().as_mut()?.allocate()?;
+	Some(PhysFrame::containing_address(PhysAddr::new(frame * FRAME_SIZE)))
+}
+
+/// free_frame() gives a frame back. it has to have come from
+/// allocate_frame(), and nothing can be using it anymore.
+pub unsafe fn free_frame(frame: PhysFrame) {
+	let number = frame.start_address().as_u64() / FRAME_SIZE;
+	let mut frames = FRAMES.lock();
+	let bitmap = frames.as_mut().expect("frame allocator isn't initialized");
+	assert!(number < bitmap.frames && bitmap.is_used(number),
+			"freeing frame {:#x}, which isn't allocated",
+			frame.start_address().as_u64());
+	bitmap.set_free(number);
+}
+
+/// frame_stats() returns how much memory there is, and how much is free
+pub fn frame_stats() -> FrameStats {
+	let mut stats = *STATS.lock();
+	stats.free = FRAMES.lock().as_ref()
+		.map_or(0, |bitmap| bitmap.free * FRAME_SIZE);
+	stats
+}
+
+/// GlobalFrameAllocator hands the global allocator to anything that wants a
+/// FrameAllocator, like the x86_64 crate's mappers
+pub struct GlobalFrameAllocator;
+
+impl FrameAllocator<Size4KiB> for GlobalFrameAllocator {
+	/// allocate_frame() is the global allocate_frame()
+	fn allocate_frame(&mut self) -> Option<PhysFrame> {
+		allocate_frame()
+	}
+}
@@ -0,0 +1,109 @@
+// file:	mmio.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Maps physical ranges (MMIO registers, firmware tables) into a
+//			window of kernel virtual memory.
+
+// includes
+use spin::Mutex;
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::{FrameAllocator, Page, PageTableFlags,
+									PhysFrame, Size4KiB};
+use super::paging;
+
+// the recursive mapping puts every p1 table in this 512GiB range
+const P1_TABLES_ADDR: u64 = 0xffff_ff80_0000_0000;
+
+// the window gets mappings handed out from it bump-allocator style. it's the
+// 512GiB covered by p4 entry 510, right below the recursive entry.
+const WINDOW_START: u64 = 0xffff_ff00_0000_0000;
+const WINDOW_END: u64 = P1_TABLES_ADDR;
+
+// how many page tables we can create for the window. the frame allocator
+// maps its bitmap through here, so they come out of a static pool in the
+// kernel's .bss instead.
+const EARLY_FRAMES: usize = 16;
+
+// EarlyFrame is a single page-aligned page for the pool
+#[derive(Clone, Copy)]
+#[repr(C, align(4096))]
+struct EarlyFrame([u8; 4096]);
+
+// the pool itself. static mut, since the cpu accesses these as page tables
+// behind rust's back; rust only ever takes their address.
+static mut EARLY_FRAME_POOL: [EarlyFrame; EARLY_FRAMES] =
+	[EarlyFrame([0; 4096]); EARLY_FRAMES];
+
+// Window tracks the next free virtual address in the window, and how many
+// pool frames have been used up for page tables
+struct Window {
+	next: u64,
+	used_frames: usize,
+}
+
+// the global window, locked for the whole of each mapping
+static WINDOW: Mutex<Window> = Mutex::new(Window {
+	next: WINDOW_START,
+	used_frames: 0,
+});
+
+// EarlyFrameAllocator hands out pool frames as page tables
+struct EarlyFrameAllocator<'a> {
+	used_frames: &'a mut usize,
+}
+
+impl<'a> FrameAllocator<Size4KiB> for EarlyFrameAllocator<'a> {
+	/// allocate_frame() returns the next unused pool frame, if there is one
+	fn allocate_frame(&mut self) -> Option<PhysFrame> {
+		if *self.used_frames >= EARLY_FRAMES {
+			return None;
+		}
+
+		let frame = unsafe { &EARLY_FRAME_POOL[*self.used_frames] };
+		*self.used_frames += 1;
+		let phys = paging::translate_addr(VirtAddr::from_ptr(frame))
+			.expect("the kernel's .bss isn't mapped");
+		Some(PhysFrame::containing_address(phys))
+	}
+}
+
+/// map_mmio() maps a range of device registers uncached, and returns the
+/// virtual address that phys ended up at.
+pub unsafe fn map_mmio(phys: PhysAddr, size: u64) -> VirtAddr {
+	map(phys, size, PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE
+		| PageTableFlags::WRITE_THROUGH)
+}
+
+/// map_physical() maps a range of normal memory, like firmware tables, and
+/// returns the virtual address that phys ended up at.
+pub unsafe fn map_physical(phys: PhysAddr, size: u64) -> VirtAddr {
+	map(phys, size, PageTableFlags::WRITABLE)
+}
+
+/// map() maps every frame touched by phys..phys+size into the window with
+/// the given flags. the mappings are permanent, so this is meant for things
+/// that get mapped once at boot.
+unsafe fn map(phys: PhysAddr, size: u64, flags: PageTableFlags) -> VirtAddr {
+	let flags = flags | PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE;
+	let start_frame: PhysFrame = PhysFrame::containing_address(phys);
+	let end_frame: PhysFrame =
+		PhysFrame::containing_address(phys + size.max(1) - 1u64);
+	let page_count = end_frame - start_frame + 1;
+
+	let mut window = WINDOW.lock();
+	let start_page: Page = Page::containing_address(VirtAddr::new(window.next));
+	assert!(window.next + page_count * 4096 <= WINDOW_END,
+			"mmio window exhausted");
+	window.next += page_count * 4096;
+
+	let mut allocator = EarlyFrameAllocator {
+		used_frames: &mut window.used_frames,
+	};
+	for i in 0..page_count {
+		paging::map_to_with(start_page + i, start_frame + i, flags,
+							&mut allocator)
+			.expect("couldn't map mmio page");
+	}
+
+	start_page.start_address() + (phys.as_u64() & 0xfff)
+}
@@ -0,0 +1,132 @@
+// file:	mod.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Memory management. the physical frame allocator, mapping pages,
+//			MMIO, guarded kernel stacks, address spaces, and somewhere to
+//			send page faults.
+
+// includes
+use bootloader::bootinfo::BootInfo;
+use crate::println;
+
+// declare the submodules
+pub mod address_space;
+pub mod fault;
+pub mod frame;
+pub mod mmio;
+pub mod paging;
+pub mod stack;
+
+// re-export the parts everyone needs
+pub use self::frame::{allocate_frame, frame_stats, free_frame, FrameStats,
+						GlobalFrameAllocator, FRAME_SIZE};
+
+// where the recursive mapping at P4 entry 511 puts each level of the tables
+const P4_ENTRIES_ADDR: u64 = 0xffff_ffff_ffff_f000;
+const P3_ENTRIES_ADDR: u64 = 0xffff_ffff_ffe0_0000;
+const P2_ENTRIES_ADDR: u64 = 0xffff_ffff_c000_0000;
+const P1_ENTRIES_ADDR: u64 = 0xffff_ff80_0000_0000;
+
+// page table entry bits we care about while walking
+const ENTRY_PRESENT: u64 = 1 << 0;
+const ENTRY_WRITABLE: u64 = 1 << 1;
+const ENTRY_USER: u64 = 1 << 2;
+const ENTRY_HUGE: u64 = 1 << 7;
+
+/// init() sets up the frame allocator from the bootloader's memory map, maps
+/// the kernel heap, gets address spaces ready, and logs what memory there is
+pub fn init(boot_info: &'static BootInfo) {
+	for region in boot_info.memory_map.iter() {
+		println!("memory: {:#012x}..{:#012x} {:?}", region.range.start_addr(),
+					region.range.end_addr(), region.region_type);
+	}
+	unsafe { frame::init(&boot_info.memory_map) };
+
+	crate::allocator::init_heap().expect("couldn't map the kernel heap");
+	println!("memory: {} KiB heap at {:#x}", crate::allocator::HEAP_SIZE / 1024,
+				crate::allocator::HEAP_START);
+	address_space::init();
+	println!("memory: address spaces {} PCIDs",
+				if address_space::pcid_enabled() { "with" } else { "without" });
+
+	let stats = frame_stats();
+	println!("memory: {} KiB total, {} KiB usable, {} KiB free",
+				stats.total / 1024, stats.usable / 1024, stats.free / 1024);
+}
+
+/// entry_addrs() returns where the p4, p3, p2 and p1 entries for addr are,
+/// through the recursive mapping. only the ones above a non-present or huge
+/// entry are actually safe to touch.
+fn entry_addrs(addr: u64) -> [u64; 4] {
+	[
+		P4_ENTRIES_ADDR | ((addr >> 36) & 0xff8),
+		P3_ENTRIES_ADDR | ((addr >> 27) & 0x1f_fff8),
+		P2_ENTRIES_ADDR | ((addr >> 18) & 0x3fff_fff8),
+		P1_ENTRIES_ADDR | ((addr >> 9) & 0x7f_ffff_fff8),
+	]
+}
+
+/// is_mapped() walks the active page tables through the recursive mapping to
+/// check whether reading addr would fault. it never touches a table that
+/// isn't present, so it's safe to call from inside exception handlers.
+pub fn is_mapped(addr: u64) -> bool {
+	use x86_64::VirtAddr;
+
+	// anything non-canonical faults no matter what the tables say
+	let high = addr >> 47;
+	if high != 0 && high != 0x1_ffff {
+		return false;
+	}
+	paging::translate_addr(VirtAddr::new(addr)).is_some()
+}
+
+/// is_user_accessible() walks the active page tables to check whether ring 3
+/// could read addr, or write it if write is set. that takes the user bit at
+/// every level of the mapping, and the writable bit too for writes, so a
+/// kernel page that merely shares a table with a user one doesn't count.
+/// like is_mapped(), it never touches a table that isn't present.
+pub fn is_user_accessible(addr: u64, write: bool) -> bool {
+	// ring 3 only ever gets the lower half
+	if addr >= 0x0000_8000_0000_0000 {
+		return false;
+	}
+
+	let mut needed = ENTRY_PRESENT | ENTRY_USER;
+	if write {
+		needed |= ENTRY_WRITABLE;
+	}
+	for (level, &entry_addr) in entry_addrs(addr).iter().enumerate() {
+		let entry = unsafe { *(entry_addr as *const u64) };
+		if entry & needed != needed {
+			return false;
+		}
+		// a huge page at the p3 or p2 level is the end of the walk
+		if (level == 1 || level == 2) && entry & ENTRY_HUGE != 0 {
+			break;
+		}
+	}
+	true
+}
+
+/// set_user_accessible() sets the user bit at every level of the mapping
+/// the page addr is on, since the tables paging::map_*() makes are kernel
+/// only. it's just for address spaces mapping their user pages, because the
+/// upper levels it touches are shared by everything mapped under them.
+/// returns false if addr isn't mapped.
+unsafe fn set_user_accessible(addr: u64) -> bool {
+	use x86_64::VirtAddr;
+	use x86_64::structures::paging::Page;
+
+	if !is_mapped(addr) {
+		return false;
+	}
+	for (level, &entry_addr) in entry_addrs(addr).iter().enumerate() {
+		let entry = entry_addr as *mut u64;
+		*entry |= ENTRY_USER;
+		if (level == 1 || level == 2) && *entry & ENTRY_HUGE != 0 {
+			break;
+		}
+	}
+	paging::flush(Page::containing_address(VirtAddr::new(addr)));
+	true
+}
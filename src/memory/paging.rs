@@ -0,0 +1,198 @@
+// file:	paging.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Mapping and unmapping pages in the active page tables, which the
+//			bootloader maps recursively at P4 entry 511.
+
+// includes
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::{FrameAllocator, Mapper, MapToError, Page,
+									PageTable, PageTableFlags, PhysFrame,
+									RecursivePageTable, Size4KiB,
+									UnmapError};
+use crate::arch::{Arch, Tlb};
+use crate::sync::IrqSpinlock;
+use super::frame::{self, GlobalFrameAllocator};
+use super::{entry_addrs, ENTRY_HUGE, ENTRY_PRESENT, P3_ENTRIES_ADDR,
+			P4_ENTRIES_ADDR};
+
+// the physical address bits of an entry, for each size of page it can map
+const ADDR_MASK_4K: u64 = 0x000f_ffff_ffff_f000;
+const ADDR_MASK_2M: u64 = 0x000f_ffff_ffe0_0000;
+const ADDR_MASK_1G: u64 = 0x000f_ffff_c000_0000;
+
+/// PagingError is why changing a mapping failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagingError {
+	// there were no frames left for a page table, or the page itself
+	OutOfFrames,
+	// the page is already mapped to something
+	AlreadyMapped,
+	// the page isn't mapped
+	NotMapped,
+	// the page is inside a huge page, which we don't split
+	HugePage,
+}
+
+impl From<MapToError> for PagingError {
+	/// from() translates the x86_64 crate's error
+	fn from(err: MapToError) -> PagingError {
+		match err {
+			MapToError::FrameAllocationFailed => PagingError::OutOfFrames,
+			MapToError::PageAlreadyMapped => PagingError::AlreadyMapped,
+			MapToError::ParentEntryHugePage => PagingError::HugePage,
+		}
+	}
+}
+
+impl From<UnmapError> for PagingError {
+	/// from() translates the x86_64 crate's error
+	fn from(err: UnmapError) -> PagingError {
+		match err {
+			UnmapError::ParentEntryHugePage => PagingError::HugePage,
+			UnmapError::PageNotMapped | UnmapError::InvalidFrameAddress(_) =>
+				PagingError::NotMapped,
+		}
+	}
+}
+
+// held while the tables are being changed, so two mappings can't both
+// decide to create the same page table. it's an IrqSpinlock, since page
+// fault resolvers map pages too.
+static TABLES: IrqSpinlock<()> = IrqSpinlock::new(());
+
+// bumped every time a mapping is taken away or changed, so address spaces
+// with their own PCID can tell if the TLB entries they left behind might be
+// stale. new mappings don't count, since the TLB never caches a miss.
+static FLUSH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// active_table() wraps the active p4 through the recursive mapping. the
+/// caller has to be holding TABLES.
+unsafe fn active_table() -> RecursivePageTable<'static> {
+	RecursivePageTable::new(&mut *(P4_ENTRIES_ADDR as *mut PageTable))
+		.expect("the bootloader's p4 isn't recursively mapped")
+}
+
+/// map_to() maps page to frame with flags, and flushes it from the TLB.
+/// PRESENT is added for you. any page tables it needs come from the frame
+/// allocator. the caller has to make sure nothing else is using frame in a
+/// way this would break.
+pub unsafe fn map_to(page: Page, frame: PhysFrame, flags: PageTableFlags)
+	-> Result<(), PagingError>
+{
+	map_to_with(page, frame, flags, &mut GlobalFrameAllocator)
+}
+
+/// map_to_with() is map_to(), but with page tables coming out of allocator
+/// instead, for callers that run before the frame allocator is up
+pub unsafe fn map_to_with<A>(page: Page, frame: PhysFrame,
+								flags: PageTableFlags, allocator: &mut A)
+	-> Result<(), PagingError>
+	where A: FrameAllocator<Size4KiB>
+{
+	// the mapper's own flush would invlpg behind Arch's back
+	let _tables = TABLES.lock();
+	active_table()
+		.map_to(page, frame, flags | PageTableFlags::PRESENT, allocator)?
+		.ignore();
+	Arch::invalidate_page(page.start_address().as_u64());
+	Ok(())
+}
+
+/// map_new() maps page to a frame fresh from the frame allocator, and
+/// returns the frame. the frame goes back if the mapping fails. its old
+/// contents are still there, so zero it if that matters.
+pub unsafe fn map_new(page: Page, flags: PageTableFlags)
+	-> Result<PhysFrame, PagingError>
+{
+	let frame = frame::allocate_frame().ok_or(PagingError::OutOfFrames)?;
+	if let Err(err) = map_to(page, frame, flags) {
+		frame::free_frame(frame);
+		return Err(err);
+	}
+	Ok(frame)
+}
+
+/// unmap() removes page's mapping, flushes it from the TLB, and returns the
+/// frame it was mapped to. the frame is left alone, so giving it back to the
+/// frame allocator is up to the caller.
+pub unsafe fn unmap(page: Page) -> Result<PhysFrame, PagingError> {
+	let _tables = TABLES.lock();
+	let (frame, flush) = active_table().unmap(page)?;
+	flush.ignore();
+	Arch::invalidate_page(page.start_address().as_u64());
+	FLUSH_GENERATION.fetch_add(1, Ordering::SeqCst);
+	Ok(frame)
+}
+
+/// populate_p4() makes sure p4 entry index has a p3 table behind it. the
+/// p3 tables are shared by every address space cloned after this, so
+/// anything mapped under the entry later shows up in all of them.
+pub fn populate_p4(index: usize) -> Result<(), PagingError> {
+	let _tables = TABLES.lock();
+	let entry = (P4_ENTRIES_ADDR + index as u64 * 8) as *mut u64;
+	unsafe {
+		if *entry & ENTRY_PRESENT != 0 {
+			return Ok(());
+		}
+		let frame = frame::allocate_frame().ok_or(PagingError::OutOfFrames)?;
+		*entry = frame.start_address().as_u64()
+			| (PageTableFlags::PRESENT | PageTableFlags::WRITABLE).bits();
+		// the new p3 shows up through the recursive mapping, so it can be
+		// cleared out there
+		let table = (P3_ENTRIES_ADDR + index as u64 * 4096) as *mut PageTable;
+		Arch::invalidate_page(table as u64);
+		(*table).zero();
+	}
+	Ok(())
+}
+
+/// translate_addr() returns the physical address addr is mapped to, or None
+/// if it isn't mapped. it reads the tables through the recursive mapping
+/// without taking any locks, and never touches a table that isn't present,
+/// so it's safe to call from exception handlers.
+pub fn translate_addr(addr: VirtAddr) -> Option<PhysAddr> {
+	let addr = addr.as_u64();
+	for (level, &entry_addr) in entry_addrs(addr).iter().enumerate() {
+		let entry = unsafe { *(entry_addr as *const u64) };
+		if entry & ENTRY_PRESENT == 0 {
+			return None;
+		}
+		// only the p3 and p2 entries can map huge pages
+		let huge = entry & ENTRY_HUGE != 0;
+		match level {
+			1 if huge => return Some(PhysAddr::new(
+				(entry & ADDR_MASK_1G) | (addr & 0x3fff_ffff))),
+			2 if huge => return Some(PhysAddr::new(
+				(entry & ADDR_MASK_2M) | (addr & 0x1f_ffff))),
+			3 => return Some(PhysAddr::new(
+				(entry & ADDR_MASK_4K) | (addr & 0xfff))),
+			_ => {},
+		}
+	}
+	None
+}
+
+/// translate_page() returns the 4KiB frame behind page, if it's mapped
+pub fn translate_page(page: Page) -> Option<PhysFrame> {
+	translate_addr(page.start_address()).map(PhysFrame::containing_address)
+}
+
+/// flush() drops a page from the TLB
+pub fn flush(page: Page) {
+	Arch::invalidate_page(page.start_address().as_u64());
+	FLUSH_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// flush_all() drops everything but global pages from the TLB
+pub fn flush_all() {
+	Arch::invalidate_all();
+	FLUSH_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// flush_generation() returns how many times a mapping's been taken away or
+/// changed. if it hasn't moved, nothing in the TLB can be stale.
+pub fn flush_generation() -> u64 {
+	FLUSH_GENERATION.load(Ordering::SeqCst)
+}
@@ -0,0 +1,190 @@
+// file:	stack.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Kernel stacks with unmapped guard pages below them, and the table
+//			of guard ranges the fault handlers check, so running off the
+//			bottom of a stack gets reported as exactly that.
+
+// includes
+use x86_64::VirtAddr;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use crate::sync::IrqSpinlock;
+use super::paging::{self, PagingError};
+
+/// the most stacks there can be, allocated and static ones together
+pub const MAX_STACKS: usize = 64;
+
+// where allocate_stack() puts stacks. it's the 512GiB under p4 entry 507,
+// right below the slab window.
+const WINDOW_START: u64 = 0xffff_fd80_0000_0000;
+// every allocated stack gets a slot this many pages big. the stack's at the
+// top of it, and everything below is left unmapped as its guard.
+const SLOT_PAGES: u64 = 64;
+const PAGE_SIZE: u64 = 4096;
+
+// the stack the bootloader left us on. it maps 512 pages up from here, and
+// nothing right below.
+const BOOT_STACK_BOTTOM: u64 = 0x57ac_0000_0000;
+const BOOT_STACK_PAGES: u64 = 512;
+
+/// StackError is why a stack couldn't be set up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+	// all MAX_STACKS stacks are taken
+	TableFull,
+	// the stack wouldn't leave room for a guard page in its slot, or has no
+	// pages at all
+	BadSize,
+	// mapping the stack, or unmapping its guard, failed
+	Paging(PagingError),
+}
+
+impl From<PagingError> for StackError {
+	/// from() wraps the paging error
+	fn from(err: PagingError) -> StackError {
+		StackError::Paging(err)
+	}
+}
+
+/// StackInfo describes a guarded stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackInfo {
+	// what the stack's called in overflow reports
+	pub name: &'static str,
+	// the lowest address of the unmapped guard below the stack
+	pub guard: VirtAddr,
+	// the lowest address of the stack itself, which is where the guard ends
+	pub bottom: VirtAddr,
+	// the address right past the top of the stack, which is where rsp starts
+	pub top: VirtAddr,
+}
+
+// every guarded stack. an allocated stack's index in here is also which
+// slot of the window it's in.
+static STACKS: IrqSpinlock<[Option<StackInfo>; MAX_STACKS]> =
+	IrqSpinlock::new([None; MAX_STACKS]);
+
+/// KernelStack is a stack from allocate_stack(). it stays mapped until it's
+/// handed to free_stack().
+#[derive(Debug)]
+pub struct KernelStack {
+	slot: usize,
+	info: StackInfo,
+}
+
+impl KernelStack {
+	/// top() returns the address right past the top of the stack, which is
+	/// what rsp should start at
+	pub fn top(&self) -> VirtAddr {
+		self.info.top
+	}
+
+	/// bottom() returns the lowest address on the stack
+	pub fn bottom(&self) -> VirtAddr {
+		self.info.bottom
+	}
+
+	/// name() returns what the stack's called
+	pub fn name(&self) -> &'static str {
+		self.info.name
+	}
+}
+
+/// init() registers the guard below the boot stack. the bootloader never
+/// maps anything there, but it's unmapped here anyway in case it ever does.
+pub fn init() {
+	let bottom = VirtAddr::new(BOOT_STACK_BOTTOM);
+	let guard = Page::containing_address(bottom - PAGE_SIZE);
+	unsafe {
+		guard_static("boot", guard, bottom + BOOT_STACK_PAGES * PAGE_SIZE)
+			.expect("couldn't guard the boot stack");
+	}
+}
+
+/// guard_static() unmaps the page below a stack that isn't ours to allocate,
+/// like the boot stack or a static one, and registers it as that stack's
+/// guard. the page's frame is left alone, since it belongs to whoever the
+/// stack does. registering the same guard twice does nothing.
+pub unsafe fn guard_static(name: &'static str, guard: Page, top: VirtAddr)
+	-> Result<(), StackError>
+{
+	let mut stacks = STACKS.lock();
+	let guard_start = guard.start_address();
+	if stacks.iter().flatten().any(|info| info.guard == guard_start) {
+		return Ok(());
+	}
+	let slot = stacks.iter().position(Option::is_none)
+		.ok_or(StackError::TableFull)?;
+
+	match paging::unmap(guard) {
+		Ok(_) | Err(PagingError::NotMapped) => {},
+		Err(err) => return Err(err.into()),
+	}
+	stacks[slot] = Some(StackInfo {
+		name,
+		guard: guard_start,
+		bottom: guard_start + PAGE_SIZE,
+		top,
+	});
+	Ok(())
+}
+
+/// allocate_stack() maps a new stack of pages pages, with everything below
+/// it in its slot left unmapped as the guard. the frame allocator has to be
+/// up.
+pub fn allocate_stack(name: &'static str, pages: u64)
+	-> Result<KernelStack, StackError>
+{
+	if pages == 0 || pages >= SLOT_PAGES {
+		return Err(StackError::BadSize);
+	}
+
+	let mut stacks = STACKS.lock();
+	let slot = stacks.iter().position(Option::is_none)
+		.ok_or(StackError::TableFull)?;
+	let guard = VirtAddr::new(WINDOW_START + slot as u64 * SLOT_PAGES
+								* PAGE_SIZE);
+	let top = guard + SLOT_PAGES * PAGE_SIZE;
+	let bottom = top - pages * PAGE_SIZE;
+
+	let first: Page = Page::containing_address(bottom);
+	let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+	for i in 0..pages {
+		if let Err(err) = unsafe { paging::map_new(first + i, flags) } {
+			unsafe { unmap_pages(first, i) };
+			return Err(err.into());
+		}
+	}
+
+	let info = StackInfo { name, guard, bottom, top };
+	stacks[slot] = Some(info);
+	Ok(KernelStack { slot, info })
+}
+
+/// free_stack() unmaps a stack from allocate_stack(), and gives its frames
+/// back. nothing can still be running on it.
+pub unsafe fn free_stack(stack: KernelStack) {
+	let mut stacks = STACKS.lock();
+	let pages = (stack.info.top - stack.info.bottom) / PAGE_SIZE;
+	unmap_pages(Page::containing_address(stack.info.bottom), pages);
+	stacks[stack.slot] = None;
+}
+
+/// unmap_pages() unmaps count pages starting at start, and frees their
+/// frames
+unsafe fn unmap_pages(start: Page, count: u64) {
+	for i in 0..count {
+		let frame = paging::unmap(start + i).expect("stack page wasn't mapped");
+		super::free_frame(frame);
+	}
+}
+
+/// overflowed_stack() returns the stack whose guard addr is in, if it's in
+/// one. it's called from the fault handlers, so if the table's locked it
+/// just says no.
+pub fn overflowed_stack(addr: VirtAddr) -> Option<StackInfo> {
+	let stacks = STACKS.try_lock()?;
+	stacks.iter().flatten()
+		.find(|info| info.guard <= addr && addr < info.bottom)
+		.cloned()
+}
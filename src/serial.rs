@@ -5,16 +5,16 @@
 
 // includes
 use lazy_static::lazy_static;
-use spin::Mutex;
 use uart_16550::SerialPort;
+use crate::sync::IrqSpinlock;
 
 // we have to lazy_static this because rust tags
 lazy_static! {
 	// ref to the serial port at the first serial port address, which is 0x3F8
-	pub static ref SERIAL_1: Mutex<SerialPort> = {
+	pub static ref SERIAL_1: IrqSpinlock<SerialPort> = {
 		let mut serial_port = SerialPort::new(0x3F8);
 		serial_port.init();
-		Mutex::new(serial_port)
+		IrqSpinlock::new(serial_port)
 	};
 }
 
@@ -25,6 +25,15 @@ pub fn _print(args: ::core::fmt::Arguments) {
 	SERIAL_1.lock().write_fmt(args).expect("Printing to Serial FAILED!");
 }
 
+// _emergency_print goes around the serial lock altogether, through the early
+// console, so it works even while somebody's holding it. at worst the output
+// interleaves with theirs. see emergency_println! and fatal_println!
+#[doc(hidden)]
+pub fn _emergency_print(args: ::core::fmt::Arguments) {
+	use core::fmt::Write;
+	let _ = crate::arch::EarlyWriter.write_fmt(args);
+}
+
 /// Prints to the serial interface
 #[macro_export]
 macro_rules! serial_print {
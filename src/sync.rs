@@ -0,0 +1,104 @@
+// file:	sync.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	Locks that are safe to take from interrupt handlers
+
+// includes
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{spin_loop_hint, AtomicBool, Ordering};
+use crate::arch::{Arch, Interrupts};
+
+// IrqSpinlock is a spinlock that disables interrupts for as long as it's
+// held, so an IRQ handler can never spin forever on a lock that the code it
+// interrupted is holding. interrupts go back to however they were before
+// once the guard is dropped.
+pub struct IrqSpinlock<T> {
+	locked: AtomicBool,
+	data: UnsafeCell<T>,
+}
+
+// IrqSpinlockGuard gives access to the data until it's dropped
+pub struct IrqSpinlockGuard<'a, T> {
+	lock: &'a IrqSpinlock<T>,
+	// whether interrupts were enabled when the lock was taken
+	were_enabled: bool,
+}
+
+unsafe impl<T: Send> Sync for IrqSpinlock<T> {}
+unsafe impl<T: Send> Send for IrqSpinlock<T> {}
+
+impl<T> IrqSpinlock<T> {
+	/// new() is the constructor for IrqSpinlock
+	pub const fn new(data: T) -> IrqSpinlock<T> {
+		IrqSpinlock {
+			locked: AtomicBool::new(false),
+			data: UnsafeCell::new(data),
+		}
+	}
+
+	/// lock() disables interrupts and spins until the lock is free
+	pub fn lock(&self) -> IrqSpinlockGuard<T> {
+		let were_enabled = Arch::interrupts_enabled();
+		Arch::disable_interrupts();
+
+		while self.locked.compare_and_swap(false, true, Ordering::Acquire) {
+			// spin on a plain load, so we aren't hammering the cache line
+			while self.locked.load(Ordering::Relaxed) {
+				spin_loop_hint();
+			}
+		}
+		IrqSpinlockGuard { lock: self, were_enabled }
+	}
+
+	/// try_lock() takes the lock if it's free, without spinning
+	pub fn try_lock(&self) -> Option<IrqSpinlockGuard<T>> {
+		let were_enabled = Arch::interrupts_enabled();
+		Arch::disable_interrupts();
+
+		if self.locked.compare_and_swap(false, true, Ordering::Acquire) {
+			if were_enabled {
+				Arch::enable_interrupts();
+			}
+			return None;
+		}
+		Some(IrqSpinlockGuard { lock: self, were_enabled })
+	}
+
+	/// is_locked() returns whether somebody is holding the lock right now
+	pub fn is_locked(&self) -> bool {
+		self.locked.load(Ordering::Relaxed)
+	}
+
+	/// force_unlock() releases the lock no matter who's holding it. this is
+	/// only for getting output out when the kernel is going down anyway, since
+	/// whoever held the lock might have left the data half-updated.
+	pub unsafe fn force_unlock(&self) {
+		self.locked.store(false, Ordering::Release);
+	}
+}
+
+impl<'a, T> Deref for IrqSpinlockGuard<'a, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		unsafe { &*self.lock.data.get() }
+	}
+}
+
+impl<'a, T> DerefMut for IrqSpinlockGuard<'a, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		unsafe { &mut *self.lock.data.get() }
+	}
+}
+
+impl<'a, T> Drop for IrqSpinlockGuard<'a, T> {
+	/// drop() releases the lock, then turns interrupts back on if they were
+	/// on to begin with
+	fn drop(&mut self) {
+		self.lock.locked.store(false, Ordering::Release);
+		if self.were_enabled {
+			Arch::enable_interrupts();
+		}
+	}
+}
@@ -0,0 +1,188 @@
+// file:	fast.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	The syscall/sysret fast path. same register ABI as int 0x80, except
+//			that syscall itself clobbers rcx and r11 (they hold the return rip
+//			and rflags), which is why the fourth argument is in r10.
+
+// includes
+use x86_64::VirtAddr;
+use x86_64::registers::model_specific::Msr;
+use x86_64::registers::rflags::RFlags;
+use x86_64::structures::paging::Page;
+use crate::gdt;
+use crate::memory::stack;
+
+// the MSRs syscall and sysret are driven by
+const IA32_EFER: u32 = 0xc000_0080;
+const IA32_STAR: u32 = 0xc000_0081;
+const IA32_LSTAR: u32 = 0xc000_0082;
+const IA32_FMASK: u32 = 0xc000_0084;
+const IA32_KERNEL_GS_BASE: u32 = 0xc000_0102;
+
+// EFER bit that turns the syscall instruction on
+const EFER_SCE: u64 = 1 << 0;
+
+// size of the kernel stack syscalls run on until someone calls
+// set_kernel_stack() with a better one
+const BOOT_STACK_SIZE: usize = 4096 * 4;
+
+// PerCpu is what KERNEL_GS_BASE points at, so the entry stub can find the
+// kernel stack with nothing but swapgs. the stub uses these by offset, so
+// don't reorder them.
+#[repr(C)]
+struct PerCpu {
+	// gs:[0], the stack to switch to on the way in
+	kernel_rsp: u64,
+	// gs:[8], somewhere to stash the user's rsp while we switch
+	user_rsp: u64,
+}
+
+// BootStack is the syscall stack we start out with. the page below it gets
+// unmapped as its guard.
+#[repr(C, align(4096))]
+struct BootStack {
+	guard: [u8; 4096],
+	stack: [u8; BOOT_STACK_SIZE],
+}
+
+// static mut, since the entry stub reads and writes these behind rust's back
+static mut PER_CPU: PerCpu = PerCpu { kernel_rsp: 0, user_rsp: 0 };
+static mut BOOT_STACK: BootStack = BootStack {
+	guard: [0; 4096],
+	stack: [0; BOOT_STACK_SIZE],
+};
+
+// SyscallFrame is what syscall_entry() leaves on the kernel stack, lowest
+// address first. changes to it get loaded back into the registers on sysret.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SyscallFrame {
+	pub r9: u64,
+	pub r8: u64,
+	pub r10: u64,
+	pub rdx: u64,
+	pub rsi: u64,
+	pub rdi: u64,
+	// the syscall number on the way in, the result on the way out
+	pub rax: u64,
+	// what syscall put in rcx and r11
+	pub rip: u64,
+	pub rflags: u64,
+	pub rsp: u64,
+}
+
+/// init() points the syscall MSRs at our entry stub and turns syscall on.
+/// the GDT has to be loaded first, since the selectors come from it.
+pub fn init() {
+	let selectors = gdt::selectors();
+	// syscall loads cs from STAR[47:32] and ss from that + 8. sysret loads
+	// ss from STAR[63:48] + 8 and cs from that + 16. the GDT is laid out so
+	// both of those work, but check anyway since a mistake here is awful to
+	// debug.
+	assert_eq!(selectors.kernel_data.0, selectors.kernel_code.0 + 8);
+	assert_eq!(selectors.user_code.0, selectors.user_data.0 + 8);
+	let sysret_base = selectors.user_data.0 - 8;
+	let star = (selectors.kernel_code.0 as u64) << 32
+		| (sysret_base as u64) << 48;
+
+	// interrupts stay off until the stub is on the kernel stack, and the
+	// rest are things the kernel doesn't want inherited from ring 3
+	let fmask = RFlags::INTERRUPT_FLAG | RFlags::DIRECTION_FLAG
+		| RFlags::TRAP_FLAG | RFlags::ALIGNMENT_CHECK;
+
+	unsafe {
+		let top = VirtAddr::from_ptr(&BOOT_STACK.stack)
+			+ BOOT_STACK_SIZE as u64;
+		let guard = Page::containing_address(
+			VirtAddr::from_ptr(&BOOT_STACK.guard));
+		stack::guard_static("syscall", guard, top)
+			.expect("couldn't guard the syscall stack");
+		set_kernel_stack(top);
+		Msr::new(IA32_KERNEL_GS_BASE).write(&PER_CPU as *const _ as u64);
+		Msr::new(IA32_STAR).write(star);
+		Msr::new(IA32_LSTAR).write(syscall_entry as u64);
+		Msr::new(IA32_FMASK).write(fmask.bits());
+
+		let mut efer = Msr::new(IA32_EFER);
+		let value = efer.read();
+		efer.write(value | EFER_SCE);
+	}
+}
+
+/// set_kernel_stack() sets the stack the next syscall runs on. whatever
+/// switches threads has to call this with the new thread's kernel stack, so
+/// every thread's syscalls run on its own stack. top has to be 16-byte
+/// aligned.
+pub unsafe fn set_kernel_stack(top: VirtAddr) {
+	debug_assert!(top.as_u64() & 0xf == 0, "misaligned kernel stack");
+	PER_CPU.kernel_rsp = top.as_u64();
+}
+
+/// jump_to_user() drops to ring 3 at entry, with rsp at stack and interrupts
+/// on. the only way back into the kernel is a syscall or an interrupt.
+pub unsafe fn jump_to_user(entry: VirtAddr, stack: VirtAddr) -> ! {
+	// sysret #GPs in ring 0 on a non-canonical rip, with the user's rsp
+	// already loaded, so don't let a kernel address anywhere near it
+	assert!(entry.as_u64() < 0x0000_8000_0000_0000,
+			"jump_to_user() to a kernel address");
+
+	asm!("cli
+			mov rsp, $2
+			sysretq"
+			:: "{rcx}"(entry.as_u64()),
+			"{r11}"(RFlags::INTERRUPT_FLAG.bits() | 1 << 1),
+			"r"(stack.as_u64())
+			:: "intel", "volatile");
+	::core::intrinsics::unreachable();
+}
+
+/// syscall_entry() is where LSTAR points. syscall doesn't switch stacks, so
+/// this swaps in the kernel's gs to find the kernel stack, saves the user's
+/// state there as a SyscallFrame, and calls syscall_handler() with it.
+/// stack alignment: 10 qwords get pushed onto a 16-byte aligned stack, which
+/// leaves rsp aligned for the call.
+/// the return rip is whatever syscall put in rcx, which is always canonical,
+/// so sysret can't fault on it as long as nobody changes frame.rip.
+#[naked]
+extern "C" fn syscall_entry() -> ! {
+	unsafe {
+		asm!("swapgs
+				mov qword ptr gs:[8], rsp
+				mov rsp, qword ptr gs:[0]
+				push qword ptr gs:[8]
+				push r11
+				push rcx
+				push rax
+				push rdi
+				push rsi
+				push rdx
+				push r10
+				push r8
+				push r9
+				sti // safe now that we're on the kernel stack
+				mov rdi, rsp // ptr to the SyscallFrame
+				call $0
+				cli // back to the user's gs and stack, without interruption
+				pop r9
+				pop r8
+				pop r10
+				pop rdx
+				pop rsi
+				pop rdi
+				pop rax
+				pop rcx
+				pop r11
+				swapgs
+				pop rsp
+				sysretq"
+				:: "i"(syscall_handler as extern "C" fn(&mut SyscallFrame))
+				:: "intel", "volatile");
+		::core::intrinsics::unreachable();
+	}
+}
+
+/// syscall_handler() hands the frame off to the generic dispatcher
+extern "C" fn syscall_handler(frame: &mut SyscallFrame) {
+	super::handle(frame);
+}
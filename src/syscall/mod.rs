@@ -0,0 +1,172 @@
+// file:	mod.rs
+// author:	garnt
+// date:	10/17/2026
+// desc:	System calls, through either int 0x80 or syscall. the number comes
+//			in rax and the arguments in rdi, rsi, rdx, r10, r8 and r9, and the
+//			result goes back out in rax. errors come back as a negative errno,
+//			the same way linux does it. syscall also clobbers rcx and r11.
+
+// declare the submodules
+mod fast;
+
+// includes
+use spin::Mutex;
+use crate::arch::ContextFrame;
+use crate::memory;
+
+// re-exports
+pub use self::fast::{jump_to_user, set_kernel_stack, SyscallFrame};
+
+/// vector that int 0x80 system calls come in at
+pub const SYSCALL_VECTOR: u8 = 0x80;
+/// number of entries in the system call table
+pub const MAX_SYSCALLS: usize = 64;
+
+/// the syscall numbers the kernel registers itself
+pub const SYS_NULL: u64 = 0;
+pub const SYS_DEBUG_WRITE: u64 = 1;
+
+// the most a single debug write is allowed to print
+const MAX_DEBUG_WRITE: u64 = 4096;
+
+/// Errno is why a system call failed. the numbers match linux's, so the
+/// usual userspace conventions work unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum Errno {
+	// operation not permitted
+	EPERM = 1,
+	// bad address
+	EFAULT = 14,
+	// resource busy
+	EBUSY = 16,
+	// invalid argument
+	EINVAL = 22,
+	// no such system call
+	ENOSYS = 38,
+}
+
+impl Errno {
+	/// as_return() encodes the error the way it goes back to the caller
+	pub fn as_return(self) -> u64 {
+		(self as u64).wrapping_neg()
+	}
+}
+
+/// SyscallArgs are the six argument registers, in ABI order
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallArgs {
+	pub rdi: u64,
+	pub rsi: u64,
+	pub rdx: u64,
+	pub r10: u64,
+	pub r8: u64,
+	pub r9: u64,
+}
+
+/// SyscallHandler is one entry in the system call table
+pub type SyscallHandler = fn(&SyscallArgs) -> Result<u64, Errno>;
+
+// the system call table, indexed by number
+static SYSCALL_TABLE: Mutex<[Option<SyscallHandler>; MAX_SYSCALLS]> =
+	Mutex::new([None; MAX_SYSCALLS]);
+
+/// init() fills in the system calls the kernel provides itself and turns on
+/// the syscall instruction. the int 0x80 gate is part of the IDT, so that's
+/// taken care of by interrupts::init().
+pub fn init() {
+	{
+		let mut table = SYSCALL_TABLE.lock();
+		table[SYS_NULL as usize] = Some(sys_null);
+		table[SYS_DEBUG_WRITE as usize] = Some(sys_debug_write);
+	}
+	fast::init();
+}
+
+/// register_syscall() adds a system call to the table. fails with EBUSY if
+/// the number is already taken, or EINVAL if it's off the end of the table.
+pub fn register_syscall(number: u64, handler: SyscallHandler)
+	-> Result<(), Errno>
+{
+	let mut table = SYSCALL_TABLE.lock();
+	let slot = table.get_mut(number as usize).ok_or(Errno::EINVAL)?;
+	if slot.is_some() {
+		return Err(Errno::EBUSY);
+	}
+	*slot = Some(handler);
+	Ok(())
+}
+
+/// unregister_syscall() removes a system call from the table
+pub fn unregister_syscall(number: u64) -> Result<(), Errno> {
+	let mut table = SYSCALL_TABLE.lock();
+	let slot = table.get_mut(number as usize).ok_or(Errno::EINVAL)?;
+	slot.take().map(|_| ()).ok_or(Errno::ENOSYS)
+}
+
+/// handle() runs the system call a frame is making, and leaves the result in
+/// it to go back to the caller. every entry path ends up here.
+pub fn handle(frame: &mut dyn ContextFrame) {
+	let args = frame.syscall_args();
+	let args = SyscallArgs {
+		rdi: args[0],
+		rsi: args[1],
+		rdx: args[2],
+		r10: args[3],
+		r8: args[4],
+		r9: args[5],
+	};
+	let result = dispatch(frame.syscall_number(), &args);
+	frame.set_return_value(result);
+}
+
+/// dispatch() runs a system call and returns what goes back in rax
+pub fn dispatch(number: u64, args: &SyscallArgs) -> u64 {
+	// copy the handler out so the table isn't locked while it runs
+	let handler = SYSCALL_TABLE.lock().get(number as usize)
+		.and_then(|handler| *handler);
+	let result = match handler {
+		Some(handler) => handler(args),
+		None => Err(Errno::ENOSYS),
+	};
+
+	match result {
+		Ok(value) => value,
+		Err(errno) => errno.as_return(),
+	}
+}
+
+/// user_slice() checks that ring 3 could have read every byte of a buffer
+/// it handed us, and returns it as a slice if it could. being mapped isn't
+/// enough, since the kernel image and its stacks are in the lower half too.
+pub fn user_slice(addr: u64, len: u64) -> Result<&'static [u8], Errno> {
+	let end = addr.checked_add(len).ok_or(Errno::EFAULT)?;
+
+	// one byte per page is enough to know the whole page is there
+	let mut page = addr & !0xfff;
+	while page < end {
+		if !memory::is_user_accessible(page, false) {
+			return Err(Errno::EFAULT);
+		}
+		page += 4096;
+	}
+	Ok(unsafe { core::slice::from_raw_parts(addr as *const u8, len as usize) })
+}
+
+/// sys_null() does nothing at all, which makes it handy for measuring how
+/// long getting in and out of the kernel takes
+fn sys_null(_args: &SyscallArgs) -> Result<u64, Errno> {
+	Ok(0)
+}
+
+/// sys_debug_write() writes a buffer to the serial port. args are the buffer
+/// and its length. returns how many bytes were written.
+fn sys_debug_write(args: &SyscallArgs) -> Result<u64, Errno> {
+	if args.rsi > MAX_DEBUG_WRITE {
+		return Err(Errno::EINVAL);
+	}
+	let buf = user_slice(args.rdi, args.rsi)?;
+	let text = core::str::from_utf8(buf).map_err(|_| Errno::EINVAL)?;
+	crate::serial_print!("{}", text);
+	Ok(buf.len() as u64)
+}
@@ -6,8 +6,8 @@
 // includes.
 use core::fmt;
 use lazy_static::lazy_static;
-use spin::Mutex;
 use volatile::Volatile;
+use crate::sync::IrqSpinlock;
 
 // Some constants defining the buffer dimensions
 const BUFFER_HEIGHT: usize = 25;
@@ -72,9 +72,11 @@ pub struct Writer {
 
 // Public static instance of Writer to be used for writing.
 // we make it a lazy_static because something something rust
-// we wrap it in a mutex so that it's "interior mutable"
+// we wrap it in a lock so that it's "interior mutable". it's an IrqSpinlock
+// so that an interrupt handler printing can't deadlock on the code it
+// interrupted.
 lazy_static! {
-	pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
+	pub static ref WRITER: IrqSpinlock<Writer> = IrqSpinlock::new(Writer {
 		column_position: 0,
 		color_code: ColorCode::new(Color::LightRed, Color::Black),
 		buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
@@ -278,3 +280,23 @@ pub fn _print(args: fmt::Arguments) {
 	use core::fmt::Write;
 	WRITER.lock().write_fmt(args).unwrap();
 }
+
+// _fatal_print() breaks the WRITER lock before printing, for when whoever
+// was holding it is never going to let go. see fatal_println!
+#[doc(hidden)]
+pub fn _fatal_print(args: fmt::Arguments) {
+	use core::fmt::Write;
+	unsafe { WRITER.force_unlock() };
+	let _ = WRITER.lock().write_fmt(args);
+}
+
+// _emergency_print() prints only if the WRITER lock is free, and drops the
+// message otherwise, since whoever's holding it is going to carry on once
+// we return. see emergency_println!
+#[doc(hidden)]
+pub fn _emergency_print(args: fmt::Arguments) {
+	use core::fmt::Write;
+	if let Some(mut writer) = WRITER.try_lock() {
+		let _ = writer.write_fmt(args);
+	}
+}